@@ -5994,22 +5994,17 @@ export default function YouTubePlaylistPlayer() {
               throw new Error('Tauri invoke not available');
             }
             
-            // Start server and get port (cached, so it's fast after first call)
-            let port;
+            // Register the file with the server (starting it if needed) and get its /media/<token> URL;
+            // the server only serves registered files and library folders
+            let streamUrl;
             try {
-              port = await invoke('start_video_server');
-              console.log("✅ [DEBUG] Video server running on port:", port);
+              streamUrl = await invoke('register_media', { path: filePath });
+              console.log("✅ [DEBUG] Using HTTP streaming URL:", streamUrl);
             } catch (serverError) {
-              console.error("❌ [DEBUG] Failed to start video server:", serverError);
+              console.error("❌ [DEBUG] Failed to register video with server:", serverError);
               throw new Error(`Failed to start video server: ${serverError.message || serverError}`);
             }
             
-            // Create HTTP URL with encoded file path
-            const encodedPath = encodeURIComponent(filePath);
-            const streamUrl = `http://127.0.0.1:${port}/video/${encodedPath}`;
-            console.log("✅ [DEBUG] Using HTTP streaming URL:", streamUrl);
-            console.log("✅ [DEBUG] Encoded path:", encodedPath);
-            
             // Add error handlers for debugging
             video.addEventListener('error', (e) => {
               console.error("❌ [DEBUG] Video element error:", e);
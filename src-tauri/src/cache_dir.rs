@@ -0,0 +1,66 @@
+// Caches kept as one folder per entry (HLS packages, seek-preview sprites). Each entry has an
+// access marker file whose modification time says when it was last used, and the cache is
+// trimmed least-recently-used first.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const ACCESS_MARKER: &str = ".last_access";
+/// Serving refreshes the access marker at most this often
+const ACCESS_TOUCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Mark an entry as used just now
+pub fn mark_accessed(dir: &Path) {
+    let _ = std::fs::write(dir.join(ACCESS_MARKER), b"");
+}
+
+/// Rewrite the access marker unless it was already written within `ACCESS_TOUCH_INTERVAL`
+/// (for requests that come in quick succession, like segments or sprite sheets)
+pub fn touch_access(dir: &Path) {
+    let recent = std::fs::metadata(dir.join(ACCESS_MARKER))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .map_or(false, |age| age < ACCESS_TOUCH_INTERVAL);
+    if !recent {
+        mark_accessed(dir);
+    }
+}
+
+/// Delete the least recently used entries under `root` until the rest fit in `max_bytes`.
+/// Entries `skip` returns true for (e.g. ones being written) are never deleted.
+/// Returns each deleted entry with its size.
+pub fn evict(root: &Path, max_bytes: u64, skip: impl Fn(&Path) -> bool) -> std::io::Result<Vec<(PathBuf, u64)>> {
+    let mut entries: Vec<(PathBuf, u64, SystemTime)> = std::fs::read_dir(root)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .map(|p| {
+            let size = crate::storage::dir_size(&p);
+            let last_used = std::fs::metadata(p.join(ACCESS_MARKER))
+                .or_else(|_| std::fs::metadata(&p))
+                .and_then(|m| m.modified())
+                .unwrap_or(UNIX_EPOCH);
+            (p, size, last_used)
+        })
+        .collect();
+
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    entries.sort_by_key(|(_, _, last_used)| *last_used);
+
+    let mut evicted = Vec::new();
+    for (dir, size, _) in entries {
+        if total <= max_bytes {
+            break;
+        }
+        if skip(&dir) {
+            continue;
+        }
+        if std::fs::remove_dir_all(&dir).is_ok() {
+            total -= size;
+            evicted.push((dir, size));
+        }
+    }
+
+    Ok(evicted)
+}
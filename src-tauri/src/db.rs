@@ -1,11 +1,19 @@
 use rusqlite::{Connection, Result, params};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use std::fs;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
-use std::io::Write;
+use crate::media::{self, StreamSelection};
+use crate::probe_cache::{self, MediaInfo};
+use crate::jobs::{self, Job, JobState};
+use crate::encoding;
+use crate::playable;
+use crate::originals::OriginalBackup;
+use crate::storage;
+use crate::thumbnails::{self, ThumbnailRecord, ThumbnailSize};
+use crate::youtube_thumbnails;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UserData {
@@ -46,6 +54,9 @@ fn default_video_progress() -> serde_json::Value {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PlaylistTab {
+    /// Stable identifier - survives renames and reordering (assigned on save if missing)
+    #[serde(default)]
+    pub id: String,
     pub name: String,
     #[serde(rename = "playlistIds")]
     pub playlist_ids: Vec<String>,
@@ -58,7 +69,11 @@ pub fn set_resource_dir(dir: Option<PathBuf>) {
     let _ = RESOURCE_DIR.set(dir);
 }
 
-fn get_db_path() -> Result<PathBuf, String> {
+pub fn get_resource_dir() -> Option<PathBuf> {
+    RESOURCE_DIR.get().cloned().flatten()
+}
+
+pub fn get_db_path() -> Result<PathBuf, String> {
     if let Some(path) = DB_PATH.get() {
         return Ok(path.clone());
     }
@@ -94,29 +109,15 @@ fn get_db_path() -> Result<PathBuf, String> {
     Ok(db_path)
 }
 
-fn get_thumbnails_dir() -> Result<PathBuf, String> {
+/// Get (and create) a cache directory next to the database, e.g. "hls_cache"
+pub fn get_cache_dir(name: &str) -> Result<PathBuf, String> {
     let db_path = get_db_path()?;
-    let thumbnails_dir = db_path.parent().unwrap().join("thumbnails");
-    
-    // Create thumbnails directory if it doesn't exist
-    std::fs::create_dir_all(&thumbnails_dir)
-        .map_err(|e| format!("Could not create thumbnails directory: {}", e))?;
+    let cache_dir = db_path.parent().unwrap().join(name);
     
-    Ok(thumbnails_dir)
-}
-
-fn get_thumbnail_path(video_id: &str) -> Result<PathBuf, String> {
-    use std::hash::{Hash, Hasher};
-    use std::collections::hash_map::DefaultHasher;
+    std::fs::create_dir_all(&cache_dir)
+        .map_err(|e| format!("Could not create {} directory: {}", name, e))?;
     
-    // Create a hash of the video ID for the filename
-    let mut hasher = DefaultHasher::new();
-    video_id.hash(&mut hasher);
-    let hash = hasher.finish();
-    let filename = format!("{:x}.jpg", hash);
-    
-    let thumbnails_dir = get_thumbnails_dir()?;
-    Ok(thumbnails_dir.join(filename))
+    Ok(cache_dir)
 }
 
 fn get_connection() -> Result<Connection> {
@@ -199,6 +200,29 @@ fn initialize_schema(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // Playlist tabs table - tabs have stable IDs so exports/imports don't depend on tab order
+    // (replaces the legacy users.playlist_tabs JSON column, which is migrated on first read)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS playlist_tabs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id TEXT NOT NULL,
+            tab_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            playlist_ids TEXT NOT NULL,
+            position INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER DEFAULT (strftime('%s', 'now')),
+            updated_at INTEGER DEFAULT (strftime('%s', 'now')),
+            UNIQUE(user_id, tab_id),
+            FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_playlist_tabs_user_id ON playlist_tabs(user_id)",
+        [],
+    )?;
+
     // Video metadata table - stores title, author, views, etc. (one-time fetch, use forever)
     conn.execute(
         "CREATE TABLE IF NOT EXISTS video_metadata (
@@ -220,6 +244,81 @@ fn initialize_schema(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // FFprobe results for local files, valid while the file's size and mtime are unchanged
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS local_media (
+            path TEXT PRIMARY KEY,
+            size INTEGER NOT NULL,
+            mtime INTEGER NOT NULL,
+            container TEXT,
+            video_codec TEXT,
+            audio_codec TEXT,
+            duration REAL,
+            width INTEGER,
+            height INTEGER,
+            bit_rate INTEGER,
+            moov_at_start INTEGER,
+            streams TEXT NOT NULL,
+            probed_at INTEGER DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    )?;
+
+    // Conversion jobs (see jobs.rs); request holds the job kind, input/output and options as JSON
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS conversion_jobs (
+            job_id TEXT PRIMARY KEY,
+            request TEXT NOT NULL,
+            state TEXT NOT NULL,
+            percent REAL,
+            current_file TEXT,
+            result TEXT,
+            error TEXT,
+            retry_of TEXT,
+            created_at INTEGER NOT NULL,
+            started_at INTEGER,
+            finished_at INTEGER
+        )",
+        [],
+    )?;
+
+    // Originals replaced by in-place conversions, kept for a while (see originals.rs)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS original_backups (
+            backup_path TEXT PRIMARY KEY,
+            original_path TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            reason TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Stored video thumbnails, one row per size, for LRU eviction and the orphan sweep (see thumbnails.rs)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS thumbnails (
+            video_id TEXT NOT NULL,
+            size TEXT NOT NULL,
+            path TEXT NOT NULL,
+            bytes INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            last_accessed INTEGER NOT NULL,
+            PRIMARY KEY (video_id, size)
+        )",
+        [],
+    )?;
+
+    // App-wide settings (key -> JSON value), e.g. library roots served by the video server
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER DEFAULT (strftime('%s', 'now'))
+        )",
+        [],
+    )?;
+
     Ok(())
 }
 
@@ -417,6 +516,376 @@ fn initialize_default_channels(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// Read an app setting (JSON-encoded value)
+pub fn get_setting<T: serde::de::DeserializeOwned>(key: &str) -> Result<Option<T>, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    
+    let value: Option<String> = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?",
+        params![key],
+        |row| row.get(0),
+    ).map(Some).or_else(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => Ok(None),
+        e => Err(e.to_string()),
+    })?;
+    
+    match value {
+        Some(json) => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| format!("Invalid value for setting '{}': {}", key, e)),
+        None => Ok(None),
+    }
+}
+
+/// Write an app setting (stored as JSON)
+pub fn set_setting<T: Serialize>(key: &str, value: &T) -> Result<(), String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at)
+         VALUES (?, ?, strftime('%s', 'now'))
+         ON CONFLICT(key) DO UPDATE SET
+           value = excluded.value,
+           updated_at = strftime('%s', 'now')",
+        params![key, json],
+    ).map_err(|e| e.to_string())?;
+    
+    Ok(())
+}
+
+/// Cached probe result for a local file, if the file hasn't changed since it was probed
+pub fn get_local_media(path: &str, size: u64, mtime: i64) -> Result<Option<MediaInfo>, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+
+    conn.query_row(LOCAL_MEDIA_QUERY, params![path, size as i64, mtime], |row| local_media_from_row(row, path, size, mtime))
+        .map(Some)
+        .or_else(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => Ok(None),
+            e => Err(e.to_string()),
+        })
+}
+
+/// Cached probe results for many files at once (path, size, mtime), over one connection.
+/// Files that haven't been probed since they last changed are missing from the result.
+pub fn get_local_media_batch(files: &[(String, u64, i64)]) -> Result<HashMap<String, MediaInfo>, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(LOCAL_MEDIA_QUERY).map_err(|e| e.to_string())?;
+
+    let mut found = HashMap::new();
+    for (path, size, mtime) in files {
+        let info = stmt.query_row(params![path, *size as i64, mtime], |row| local_media_from_row(row, path, *size, *mtime));
+        match info {
+            Ok(info) => {
+                found.insert(path.clone(), info);
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    Ok(found)
+}
+
+const LOCAL_MEDIA_QUERY: &str =
+    "SELECT container, video_codec, audio_codec, duration, width, height, bit_rate, moov_at_start, streams
+     FROM local_media WHERE path = ? AND size = ? AND mtime = ?";
+
+fn local_media_from_row(row: &rusqlite::Row, path: &str, size: u64, mtime: i64) -> rusqlite::Result<MediaInfo> {
+    let streams: String = row.get(8)?;
+    Ok(MediaInfo {
+        path: path.to_string(),
+        size,
+        mtime,
+        container: row.get(0)?,
+        video_codec: row.get(1)?,
+        audio_codec: row.get(2)?,
+        duration: row.get(3)?,
+        width: row.get(4)?,
+        height: row.get(5)?,
+        bit_rate: row.get::<_, Option<i64>>(6)?.map(|b| b as u64),
+        moov_at_start: row.get(7)?,
+        streams: serde_json::from_str(&streams).unwrap_or_default(),
+    })
+}
+
+/// Store (or replace) a local file's probe result
+pub fn save_local_media(info: &MediaInfo) -> Result<(), String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    let streams = serde_json::to_string(&info.streams).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO local_media
+         (path, size, mtime, container, video_codec, audio_codec, duration, width, height, bit_rate, moov_at_start, streams, probed_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, strftime('%s', 'now'))",
+        params![
+            info.path,
+            info.size as i64,
+            info.mtime,
+            info.container,
+            info.video_codec,
+            info.audio_codec,
+            info.duration,
+            info.width,
+            info.height,
+            info.bit_rate.map(|b| b as i64),
+            info.moov_at_start,
+            streams,
+        ],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Insert or update a conversion job
+pub fn save_job(job: &Job) -> Result<(), String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    let request = serde_json::to_string(&job.request).map_err(|e| e.to_string())?;
+    let result = job.result.as_ref().map(|r| r.to_string());
+
+    conn.execute(
+        "INSERT OR REPLACE INTO conversion_jobs
+         (job_id, request, state, percent, current_file, result, error, retry_of, created_at, started_at, finished_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            job.id,
+            request,
+            job.state.as_str(),
+            job.percent,
+            job.current_file,
+            result,
+            job.error,
+            job.retry_of,
+            job.created_at as i64,
+            job.started_at.map(|t| t as i64),
+            job.finished_at.map(|t| t as i64),
+        ],
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// All stored conversion jobs, oldest first (rows with an unreadable request are skipped)
+pub fn load_jobs() -> Result<Vec<Job>, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT job_id, request, state, percent, current_file, result, error, retry_of, created_at, started_at, finished_at
+         FROM conversion_jobs ORDER BY created_at, job_id"
+    ).map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<f64>>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, Option<String>>(5)?,
+            row.get::<_, Option<String>>(6)?,
+            row.get::<_, Option<String>>(7)?,
+            row.get::<_, i64>(8)?,
+            row.get::<_, Option<i64>>(9)?,
+            row.get::<_, Option<i64>>(10)?,
+        ))
+    }).map_err(|e| e.to_string())?;
+
+    let mut jobs = Vec::new();
+    for row in rows {
+        let (id, request, state, percent, current_file, result, error, retry_of, created_at, started_at, finished_at) =
+            row.map_err(|e| e.to_string())?;
+        let request = match serde_json::from_str(&request) {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("⚠️ Skipping job {} with unreadable request: {}", id, e);
+                continue;
+            }
+        };
+        jobs.push(Job {
+            id,
+            request,
+            state: JobState::parse(&state).unwrap_or(JobState::Failed),
+            percent,
+            eta_seconds: None,
+            current_file,
+            result: result.and_then(|r| serde_json::from_str(&r).ok()),
+            error,
+            retry_of,
+            created_at: created_at as u64,
+            started_at: started_at.map(|t| t as u64),
+            finished_at: finished_at.map(|t| t as u64),
+        });
+    }
+
+    Ok(jobs)
+}
+
+/// Delete finished jobs that ended before `before` (unix seconds). Returns the number deleted.
+pub fn delete_finished_jobs(before: u64) -> Result<usize, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM conversion_jobs WHERE state IN ('completed', 'failed', 'cancelled') AND finished_at < ?",
+        params![before as i64],
+    ).map_err(|e| e.to_string())
+}
+
+pub fn save_original_backup(backup: &OriginalBackup) -> Result<(), String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO original_backups (backup_path, original_path, size, reason, created_at)
+         VALUES (?, ?, ?, ?, ?)",
+        params![backup.backup_path, backup.original_path, backup.size as i64, backup.reason, backup.created_at as i64],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Kept originals, newest first (all of them, or those of one file)
+pub fn load_original_backups(original_path: Option<&str>) -> Result<Vec<OriginalBackup>, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT backup_path, original_path, size, reason, created_at FROM original_backups
+         WHERE ?1 IS NULL OR original_path = ?1
+         ORDER BY created_at DESC"
+    ).map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map(params![original_path], |row| {
+        Ok(OriginalBackup {
+            backup_path: row.get(0)?,
+            original_path: row.get(1)?,
+            size: row.get::<_, i64>(2)? as u64,
+            reason: row.get(3)?,
+            created_at: row.get::<_, i64>(4)? as u64,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+pub fn delete_original_backup(backup_path: &str) -> Result<(), String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM original_backups WHERE backup_path = ?", params![backup_path])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn save_thumbnail_record(record: &ThumbnailRecord) -> Result<(), String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO thumbnails (video_id, size, path, bytes, source, created_at, last_accessed)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![
+            record.video_id, record.size.as_str(), record.path, record.bytes as i64,
+            record.source, record.created_at as i64, record.last_accessed as i64
+        ],
+    ).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Indexed thumbnails, least recently used first
+pub fn load_thumbnail_records() -> Result<Vec<ThumbnailRecord>, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT video_id, size, path, bytes, source, created_at, last_accessed FROM thumbnails
+         ORDER BY last_accessed ASC, created_at ASC"
+    ).map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map([], |row| {
+        let size: String = row.get(1)?;
+        Ok(ThumbnailRecord {
+            video_id: row.get(0)?,
+            size: ThumbnailSize::parse(&size).unwrap_or(ThumbnailSize::Small),
+            path: row.get(2)?,
+            bytes: row.get::<_, i64>(3)? as u64,
+            source: row.get(4)?,
+            created_at: row.get::<_, i64>(5)? as u64,
+            last_accessed: row.get::<_, i64>(6)? as u64,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())
+}
+
+/// Total size of the indexed thumbnails
+pub fn thumbnail_cache_bytes() -> Result<u64, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    conn.query_row("SELECT COALESCE(SUM(bytes), 0) FROM thumbnails", [], |row| row.get::<_, i64>(0))
+        .map(|bytes| bytes as u64)
+        .map_err(|e| e.to_string())
+}
+
+pub fn load_thumbnail_record(video_id: &str, size: ThumbnailSize) -> Result<Option<ThumbnailRecord>, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    conn.query_row(
+        "SELECT path, bytes, source, created_at, last_accessed FROM thumbnails WHERE video_id = ? AND size = ?",
+        params![video_id, size.as_str()],
+        |row| {
+            Ok(ThumbnailRecord {
+                video_id: video_id.to_string(),
+                size,
+                path: row.get(0)?,
+                bytes: row.get::<_, i64>(1)? as u64,
+                source: row.get(2)?,
+                created_at: row.get::<_, i64>(3)? as u64,
+                last_accessed: row.get::<_, i64>(4)? as u64,
+            })
+        },
+    ).map(Some).or_else(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => Ok(None),
+        e => Err(e.to_string()),
+    })
+}
+
+/// Write many thumbnails' access times in one transaction; unindexed ones are skipped
+pub fn touch_thumbnail_records(accesses: &[(String, ThumbnailSize, u64)]) -> Result<(), String> {
+    let mut conn = get_connection().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    {
+        let mut stmt = tx.prepare("UPDATE thumbnails SET last_accessed = ? WHERE video_id = ? AND size = ?")
+            .map_err(|e| e.to_string())?;
+        for (video_id, size, accessed) in accesses {
+            stmt.execute(params![*accessed as i64, video_id, size.as_str()]).map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+pub fn delete_thumbnail_record(video_id: &str, size: ThumbnailSize) -> Result<(), String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM thumbnails WHERE video_id = ? AND size = ?", params![video_id, size.as_str()])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Video IDs of one playlist, in order
+pub fn load_playlist_videos(user_id: &str, playlist_id: &str) -> Result<Vec<String>, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    let videos_json: String = conn.query_row(
+        "SELECT videos FROM playlists WHERE user_id = ? AND playlist_id = ?",
+        params![user_id, playlist_id],
+        |row| row.get(0),
+    ).map_err(|e| format!("Playlist not found: {}", e))?;
+    serde_json::from_str(&videos_json).map_err(|e| format!("Failed to parse playlist videos: {}", e))
+}
+
+/// Every video ID used by any user's playlists (videos and representative videos)
+pub fn load_playlist_video_ids() -> Result<HashSet<String>, String> {
+    let conn = get_connection().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT videos, representative_video_id FROM playlists")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+    }).map_err(|e| e.to_string())?;
+
+    let mut ids = HashSet::new();
+    for row in rows {
+        let (videos_json, representative) = row.map_err(|e| e.to_string())?;
+        let videos: Vec<String> = serde_json::from_str(&videos_json)
+            .map_err(|e| format!("Failed to parse playlist videos: {}", e))?;
+        ids.extend(videos);
+        ids.extend(representative);
+    }
+    Ok(ids)
+}
+
 #[tauri::command]
 pub fn test_db_connection() -> Result<String, String> {
     let db_path = get_db_path().map_err(|e| format!("Failed to get database path: {}", e))?;
@@ -579,7 +1048,7 @@ pub fn get_user_data(user_id: String) -> Result<UserData, String> {
         ))
     });
     
-    let (custom_colors, color_order, playlist_tabs, video_progress) = match user_row {
+    let (custom_colors, color_order, legacy_playlist_tabs, video_progress) = match user_row {
         Ok(row) => row,
         Err(_) => {
             // User doesn't exist - copy from default template
@@ -615,6 +1084,14 @@ pub fn get_user_data(user_id: String) -> Result<UserData, String> {
                     ).map_err(|e| e.to_string())?;
                     eprintln!("✅ Copied {} default playlists to user {}", copied, user_id);
                     
+                    // Copy default tabs too (if the template's tabs were already migrated to the table)
+                    conn.execute(
+                        "INSERT INTO playlist_tabs (user_id, tab_id, name, playlist_ids, position)
+                         SELECT ?, tab_id, name, playlist_ids, position
+                         FROM playlist_tabs WHERE user_id = 'default'",
+                        params![user_id],
+                    ).map_err(|e| e.to_string())?;
+                    
                     row
                 }
                 Err(_) => (None, None, None, None),
@@ -646,20 +1123,156 @@ pub fn get_user_data(user_id: String) -> Result<UserData, String> {
     .collect::<Result<Vec<_>, _>>()
     .map_err(|e| e.to_string())?;
     
+    // Get tabs (migrating the legacy JSON column into the tabs table if needed)
+    migrate_legacy_tabs(&conn, &user_id, legacy_playlist_tabs).map_err(|e| e.to_string())?;
+    let playlist_tabs = load_tabs(&conn, &user_id).map_err(|e| e.to_string())?;
+    
     Ok(UserData {
         playlists,
-        playlist_tabs: serde_json::from_str(&playlist_tabs.unwrap_or_default()).unwrap_or_default(),
+        playlist_tabs,
         custom_colors: serde_json::from_str(&custom_colors.unwrap_or_default()).unwrap_or(serde_json::json!({})),
         color_order: serde_json::from_str(&color_order.unwrap_or_default()).unwrap_or_default(),
         video_progress: serde_json::from_str(&video_progress.unwrap_or_default()).unwrap_or(serde_json::json!({})),
     })
 }
 
+/// A tab entry pointing at a playlist that doesn't exist in the library
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DanglingTabReference {
+    pub tab_id: String,
+    pub tab_name: String,
+    pub playlist_id: String,
+}
+
+/// Color groups every playlist gets (`createDefaultGroups` in the frontend). They are kept
+/// even when empty, since the frontend recreates them and their names may have been edited.
+const BUILTIN_GROUP_KEYS: [&str; 9] = ["red", "green", "blue", "yellow", "orange", "purple", "pink", "cyan", "indigo"];
+
+/// A custom (non built-in) group with no videos in it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmptyGroup {
+    pub playlist_id: String,
+    pub group: String,
+}
+
+/// Result of checking a library's references (returned by save_user_data and repair_library)
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LibraryReport {
+    /// Tab entries referencing playlists that aren't in the library
+    pub dangling_tab_refs: Vec<DanglingTabReference>,
+    /// Tabs whose playlist references are all dangling (IDs)
+    pub orphan_tabs: Vec<String>,
+    /// Playlist IDs used by more than one playlist
+    pub duplicate_playlist_ids: Vec<String>,
+    /// Custom groups that contain no videos (built-in color groups are never reported)
+    pub empty_groups: Vec<EmptyGroup>,
+    /// Whether the issues above were fixed before saving
+    pub repaired: bool,
+}
+
+impl LibraryReport {
+    fn has_reference_issues(&self) -> bool {
+        !self.dangling_tab_refs.is_empty() || !self.duplicate_playlist_ids.is_empty()
+    }
+}
+
+/// Check tabs and playlists for broken references (doesn't modify anything)
+fn check_library(data: &UserData) -> LibraryReport {
+    let mut report = LibraryReport::default();
+    
+    let mut playlist_ids: HashSet<&str> = HashSet::new();
+    for playlist in &data.playlists {
+        if !playlist_ids.insert(playlist.id.as_str()) && !report.duplicate_playlist_ids.contains(&playlist.id) {
+            report.duplicate_playlist_ids.push(playlist.id.clone());
+        }
+        
+        if let Some(groups) = playlist.groups.as_object() {
+            for (group, value) in groups {
+                if BUILTIN_GROUP_KEYS.contains(&group.as_str()) {
+                    continue;
+                }
+                let video_count = value.get("videos").and_then(|v| v.as_array()).map(|v| v.len()).unwrap_or(0);
+                if video_count == 0 {
+                    report.empty_groups.push(EmptyGroup {
+                        playlist_id: playlist.id.clone(),
+                        group: group.clone(),
+                    });
+                }
+            }
+        }
+    }
+    
+    for tab in &data.playlist_tabs {
+        let dangling: Vec<&String> = tab.playlist_ids.iter()
+            .filter(|id| !playlist_ids.contains(id.as_str()))
+            .collect();
+        
+        if !tab.playlist_ids.is_empty() && dangling.len() == tab.playlist_ids.len() {
+            report.orphan_tabs.push(tab.id.clone());
+        }
+        
+        report.dangling_tab_refs.extend(dangling.into_iter().map(|id| DanglingTabReference {
+            tab_id: tab.id.clone(),
+            tab_name: tab.name.clone(),
+            playlist_id: id.clone(),
+        }));
+    }
+    
+    report
+}
+
+/// Drop dangling tab references and repeated entries within a tab, and keep only the first
+/// playlist for each duplicated ID
+fn prune_dangling_references(data: &mut UserData) {
+    let mut seen_playlists: HashSet<String> = HashSet::new();
+    data.playlists.retain(|p| seen_playlists.insert(p.id.clone()));
+    
+    for tab in &mut data.playlist_tabs {
+        let mut seen_refs: HashSet<String> = HashSet::new();
+        tab.playlist_ids.retain(|id| seen_playlists.contains(id) && seen_refs.insert(id.clone()));
+    }
+}
+
+/// Remove custom groups that have no videos (built-in color groups are kept)
+fn remove_empty_groups(data: &mut UserData) {
+    for playlist in &mut data.playlists {
+        if let Some(groups) = playlist.groups.as_object_mut() {
+            groups.retain(|key, value| {
+                BUILTIN_GROUP_KEYS.contains(&key.as_str())
+                    || value.get("videos").and_then(|v| v.as_array()).map(|v| !v.is_empty()).unwrap_or(false)
+            });
+        }
+    }
+}
+
+/// Save a user's library. Broken references are reported in the returned LibraryReport;
+/// with `prune_dangling` they are removed before saving. Duplicate playlist IDs are rejected
+/// unless pruning, since the database can only hold one playlist per ID.
 #[tauri::command]
-pub fn save_user_data(user_id: String, data: UserData) -> Result<(), String> {
+pub fn save_user_data(user_id: String, mut data: UserData, prune_dangling: Option<bool>) -> Result<LibraryReport, String> {
     eprintln!("💾 save_user_data called for user_id: {}", user_id);
     eprintln!("   Saving {} playlists", data.playlists.len());
     
+    let mut report = check_library(&data);
+    let prune = prune_dangling.unwrap_or(false);
+    
+    if !report.dangling_tab_refs.is_empty() {
+        eprintln!("⚠️ {} tab reference(s) point to missing playlists", report.dangling_tab_refs.len());
+    }
+    
+    if !report.duplicate_playlist_ids.is_empty() && !prune {
+        return Err(format!(
+            "Duplicate playlist IDs: {}. Save with pruning enabled to keep only the first of each.",
+            report.duplicate_playlist_ids.join(", ")
+        ));
+    }
+    
+    if prune && report.has_reference_issues() {
+        prune_dangling_references(&mut data);
+        report.repaired = true;
+        eprintln!("🧹 Pruned dangling tab references and duplicate playlists before saving");
+    }
+    
     let mut conn = get_connection().map_err(|e| {
         eprintln!("❌ Failed to get database connection: {}", e);
         e.to_string()
@@ -670,25 +1283,29 @@ pub fn save_user_data(user_id: String, data: UserData) -> Result<(), String> {
         e.to_string()
     })?;
     
-    // Upsert user record
+    // Upsert user record (tabs live in the playlist_tabs table, so the legacy column is cleared)
     tx.execute(
         "INSERT INTO users (user_id, custom_colors, color_order, playlist_tabs, video_progress, updated_at)
-         VALUES (?, ?, ?, ?, ?, strftime('%s', 'now'))
+         VALUES (?, ?, ?, NULL, ?, strftime('%s', 'now'))
          ON CONFLICT(user_id) DO UPDATE SET
            custom_colors = excluded.custom_colors,
            color_order = excluded.color_order,
-           playlist_tabs = excluded.playlist_tabs,
+           playlist_tabs = NULL,
            video_progress = excluded.video_progress,
            updated_at = strftime('%s', 'now')",
         params![
             user_id,
             serde_json::to_string(&data.custom_colors).map_err(|e| e.to_string())?,
             serde_json::to_string(&data.color_order).map_err(|e| e.to_string())?,
-            serde_json::to_string(&data.playlist_tabs).map_err(|e| e.to_string())?,
             serde_json::to_string(&data.video_progress).map_err(|e| e.to_string())?,
         ],
     ).map_err(|e| e.to_string())?;
     
+    // Save tabs, keeping the IDs of existing tabs when the frontend sends tabs without IDs
+    let existing_tabs = load_tabs(&tx, &user_id).map_err(|e| e.to_string())?;
+    let tabs = assign_tab_ids(data.playlist_tabs, &existing_tabs);
+    write_tabs(&tx, &user_id, &tabs).map_err(|e| e.to_string())?;
+    
     // Delete existing playlists for this user
     tx.execute("DELETE FROM playlists WHERE user_id = ?", params![user_id])
         .map_err(|e| e.to_string())?;
@@ -743,7 +1360,37 @@ pub fn save_user_data(user_id: String, data: UserData) -> Result<(), String> {
         return Err("Save verification failed: playlists were not persisted".to_string());
     }
     
-    Ok(())
+    Ok(report)
+}
+
+/// Check a user's library for orphan tabs, dangling tab references, duplicate playlist IDs and
+/// empty custom groups. With `apply`, dangling references are pruned and orphan tabs and empty
+/// custom groups removed.
+#[tauri::command]
+pub fn repair_library(user_id: String, apply: Option<bool>) -> Result<LibraryReport, String> {
+    eprintln!("🔧 repair_library called for user_id: {}, apply: {:?}", user_id, apply);
+    
+    let mut data = get_user_data(user_id.clone())?;
+    let mut report = check_library(&data);
+    
+    eprintln!(
+        "   Found {} dangling tab refs, {} orphan tabs, {} duplicate playlist IDs, {} empty groups",
+        report.dangling_tab_refs.len(),
+        report.orphan_tabs.len(),
+        report.duplicate_playlist_ids.len(),
+        report.empty_groups.len()
+    );
+    
+    let has_issues = report.has_reference_issues() || !report.orphan_tabs.is_empty() || !report.empty_groups.is_empty();
+    if apply.unwrap_or(false) && has_issues {
+        remove_empty_groups(&mut data);
+        data.playlist_tabs.retain(|tab| !report.orphan_tabs.contains(&tab.id));
+        save_user_data(user_id, data, Some(true))?;
+        report.repaired = true;
+        eprintln!("✅ Library repaired");
+    }
+    
+    Ok(report)
 }
 
 /// Import a playlist from a JSON file (safe - only adds, never deletes or modifies existing)
@@ -810,7 +1457,7 @@ pub fn import_playlist_file(user_id: String, file_path: String) -> Result<String
     }
     
     // Save the updated data (this will preserve tabs, colors, progress - only playlists change)
-    save_user_data(user_id.clone(), current_data)
+    save_user_data(user_id.clone(), current_data, None)
         .map_err(|e| format!("Failed to save imported playlists: {}", e))?;
     
     if added_count == 0 {
@@ -874,25 +1521,164 @@ pub fn overwrite_playlist_file(user_id: String, playlist_id: String, file_path:
     }
     
     // Save the updated data
-    save_user_data(user_id.clone(), current_data)
+    save_user_data(user_id.clone(), current_data, None)
         .map_err(|e| format!("Failed to save overwritten playlist: {}", e))?;
     
     Ok(format!("Successfully overwrote playlist '{}'", playlist_name))
 }
 
-/// Export a tab with all its playlists as JSON
-#[tauri::command]
-pub fn export_tab(user_id: String, tab_index: usize) -> Result<String, String> {
-    eprintln!("📤 export_tab called for user_id: {}, tab_index: {}", user_id, tab_index);
+/// Generate a new stable tab ID (timestamp + counter, unique within this process)
+fn generate_tab_id() -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("tab_{}_{}", millis, n)
+}
+
+/// Load a user's tabs in display order
+fn load_tabs(conn: &Connection, user_id: &str) -> Result<Vec<PlaylistTab>> {
+    let mut stmt = conn.prepare(
+        "SELECT tab_id, name, playlist_ids FROM playlist_tabs
+         WHERE user_id = ? ORDER BY position, id"
+    )?;
     
-    let current_data = get_user_data(user_id.clone())
-        .map_err(|e| format!("Failed to get current user data: {}", e))?;
+    let tabs = stmt.query_map(params![user_id], |row| {
+        Ok(PlaylistTab {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            playlist_ids: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+        })
+    })?
+    .collect::<Result<Vec<_>>>()?;
     
-    if tab_index >= current_data.playlist_tabs.len() {
-        return Err(format!("Tab index {} out of range ({} tabs available)", tab_index, current_data.playlist_tabs.len()));
-    }
+    Ok(tabs)
+}
+
+/// Replace a user's tabs (position = index in the slice)
+fn write_tabs(conn: &Connection, user_id: &str, tabs: &[PlaylistTab]) -> Result<()> {
+    conn.execute("DELETE FROM playlist_tabs WHERE user_id = ?", params![user_id])?;
     
-    let tab = &current_data.playlist_tabs[tab_index];
+    let mut stmt = conn.prepare(
+        "INSERT INTO playlist_tabs (user_id, tab_id, name, playlist_ids, position, updated_at)
+         VALUES (?, ?, ?, ?, ?, strftime('%s', 'now'))"
+    )?;
+    
+    for (position, tab) in tabs.iter().enumerate() {
+        stmt.execute(params![
+            user_id,
+            tab.id,
+            tab.name,
+            serde_json::to_string(&tab.playlist_ids).unwrap_or_else(|_| "[]".to_string()),
+            position as i64,
+        ])?;
+    }
+    
+    Ok(())
+}
+
+/// Give every tab an ID. Tabs sent without one (older frontend state) reuse the ID of an
+/// existing tab with the same name, so saving doesn't churn IDs; otherwise a new ID is generated.
+fn assign_tab_ids(tabs: Vec<PlaylistTab>, existing: &[PlaylistTab]) -> Vec<PlaylistTab> {
+    let reserved: HashSet<String> = tabs.iter()
+        .filter(|t| !t.id.is_empty())
+        .map(|t| t.id.clone())
+        .collect();
+    let mut seen: HashSet<String> = HashSet::new();
+    
+    tabs.into_iter()
+        .map(|mut tab| {
+            // Missing or duplicate IDs get reassigned
+            if tab.id.is_empty() || seen.contains(&tab.id) {
+                tab.id = existing.iter()
+                    .find(|e| e.name == tab.name && !reserved.contains(&e.id) && !seen.contains(&e.id))
+                    .map(|e| e.id.clone())
+                    .unwrap_or_else(generate_tab_id);
+            }
+            seen.insert(tab.id.clone());
+            tab
+        })
+        .collect()
+}
+
+/// Move tabs from the legacy users.playlist_tabs JSON column into the playlist_tabs table.
+/// Only runs when the user has no tab rows yet; clears the legacy column afterwards.
+fn migrate_legacy_tabs(conn: &Connection, user_id: &str, legacy_json: Option<String>) -> Result<()> {
+    let legacy_json = match legacy_json {
+        Some(json) if !json.trim().is_empty() => json,
+        _ => return Ok(()),
+    };
+    
+    let existing: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM playlist_tabs WHERE user_id = ?",
+        params![user_id],
+        |row| row.get(0),
+    )?;
+    
+    if existing == 0 {
+        let legacy_tabs: Vec<PlaylistTab> = serde_json::from_str(&legacy_json).unwrap_or_default();
+        let tabs = assign_tab_ids(legacy_tabs, &[]);
+        write_tabs(conn, user_id, &tabs)?;
+        eprintln!("✅ Migrated {} tabs to playlist_tabs table for user {}", tabs.len(), user_id);
+    }
+    
+    conn.execute(
+        "UPDATE users SET playlist_tabs = NULL WHERE user_id = ?",
+        params![user_id],
+    )?;
+    
+    Ok(())
+}
+
+/// Load, modify and save a user's tabs in one transaction (used by the tab CRUD commands)
+fn update_tabs<T>(user_id: &str, f: impl FnOnce(&mut Vec<PlaylistTab>) -> Result<T, String>) -> Result<T, String> {
+    // Make sure the user exists (copies the default template on first use) and tabs are migrated
+    get_user_data(user_id.to_string())?;
+    
+    let mut conn = get_connection().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    
+    let mut tabs = load_tabs(&tx, user_id).map_err(|e| e.to_string())?;
+    let result = f(&mut tabs)?;
+    write_tabs(&tx, user_id, &tabs).map_err(|e| e.to_string())?;
+    
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+fn find_tab_mut<'a>(tabs: &'a mut [PlaylistTab], tab_id: &str) -> Result<&'a mut PlaylistTab, String> {
+    tabs.iter_mut()
+        .find(|t| t.id == tab_id)
+        .ok_or_else(|| format!("Tab with ID '{}' not found", tab_id))
+}
+
+/// Export a tab with all its playlists as JSON.
+/// The tab is looked up by ID, then by name, then by index (for older callers).
+#[tauri::command]
+pub fn export_tab(user_id: String, tab_id: Option<String>, tab_name: Option<String>, tab_index: Option<usize>) -> Result<String, String> {
+    eprintln!("📤 export_tab called for user_id: {}, tab_id: {:?}, tab_name: {:?}, tab_index: {:?}", user_id, tab_id, tab_name, tab_index);
+    
+    let current_data = get_user_data(user_id.clone())
+        .map_err(|e| format!("Failed to get current user data: {}", e))?;
+    
+    let tab = if let Some(id) = &tab_id {
+        current_data.playlist_tabs.iter()
+            .find(|t| &t.id == id)
+            .ok_or_else(|| format!("Tab with ID '{}' not found", id))?
+    } else if let Some(name) = &tab_name {
+        current_data.playlist_tabs.iter()
+            .find(|t| &t.name == name)
+            .ok_or_else(|| format!("Tab named '{}' not found", name))?
+    } else if let Some(index) = tab_index {
+        current_data.playlist_tabs.get(index)
+            .ok_or_else(|| format!("Tab index {} out of range ({} tabs available)", index, current_data.playlist_tabs.len()))?
+    } else {
+        return Err("Specify a tab ID, name or index to export".to_string());
+    };
     
     // Get all playlists in this tab
     let tab_playlists: Vec<Playlist> = current_data.playlists.iter()
@@ -903,6 +1689,7 @@ pub fn export_tab(user_id: String, tab_index: usize) -> Result<String, String> {
     // Create export structure
     let export_data = serde_json::json!({
         "tab": {
+            "id": tab.id,
             "name": tab.name,
             "playlistIds": tab.playlist_ids
         },
@@ -916,9 +1703,12 @@ pub fn export_tab(user_id: String, tab_index: usize) -> Result<String, String> {
     Ok(json)
 }
 
-/// Import a tab file (creates tab and imports playlists)
+/// Import a tab file (imports playlists, then updates the matching tab or creates a new one).
+/// A tab matches by ID; files exported before tabs had IDs match by name.
+/// Tab entries for playlists that are neither in the file nor the library are reported,
+/// and removed from the tab when `prune_missing` is set.
 #[tauri::command]
-pub fn import_tab_file(user_id: String, file_path: String) -> Result<String, String> {
+pub fn import_tab_file(user_id: String, file_path: String, prune_missing: Option<bool>) -> Result<String, String> {
     eprintln!("📥 import_tab_file called for user_id: {}, file: {}", user_id, file_path);
     
     // Read and parse the file
@@ -932,12 +1722,17 @@ pub fn import_tab_file(user_id: String, file_path: String) -> Result<String, Str
     let tab_data = json_data.get("tab")
         .ok_or_else(|| "File must contain a 'tab' object".to_string())?;
     
+    let tab_id = tab_data.get("id")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    
     let tab_name = tab_data.get("name")
         .and_then(|v| v.as_str())
         .ok_or_else(|| "Tab must have a 'name' field".to_string())?
         .to_string();
     
-    let tab_playlist_ids: Vec<String> = tab_data.get("playlistIds")
+    let mut tab_playlist_ids: Vec<String> = tab_data.get("playlistIds")
         .and_then(|v| v.as_array())
         .map(|arr| {
             arr.iter()
@@ -983,19 +1778,139 @@ pub fn import_tab_file(user_id: String, file_path: String) -> Result<String, Str
         }
     }
     
-    // Create new tab
-    let new_tab = PlaylistTab {
-        name: tab_name.clone(),
-        playlist_ids: tab_playlist_ids.clone(),
+    // Check the tab's references against the merged library
+    let library_ids: HashSet<&str> = current_data.playlists.iter()
+        .map(|p| p.id.as_str())
+        .collect();
+    let missing_ids: Vec<String> = tab_playlist_ids.iter()
+        .filter(|id| !library_ids.contains(id.as_str()))
+        .cloned()
+        .collect();
+    let prune = prune_missing.unwrap_or(false);
+    
+    if !missing_ids.is_empty() {
+        eprintln!("   ⚠️ Tab references {} missing playlist(s): {:?}", missing_ids.len(), missing_ids);
+        if prune {
+            tab_playlist_ids.retain(|id| !missing_ids.contains(id));
+        }
+    }
+    
+    // Update the matching tab, or create a new one
+    let existing_tab = if !tab_id.is_empty() {
+        current_data.playlist_tabs.iter_mut().find(|t| t.id == tab_id)
+    } else {
+        current_data.playlist_tabs.iter_mut().find(|t| t.name == tab_name)
     };
     
-    current_data.playlist_tabs.push(new_tab);
+    let tab_action = match existing_tab {
+        Some(tab) => {
+            eprintln!("   🔄 Updating existing tab '{}' (ID: {})", tab.name, tab.id);
+            tab.name = tab_name.clone();
+            tab.playlist_ids = tab_playlist_ids;
+            "updated"
+        }
+        None => {
+            current_data.playlist_tabs.push(PlaylistTab {
+                id: tab_id,
+                name: tab_name.clone(),
+                playlist_ids: tab_playlist_ids,
+            });
+            "created"
+        }
+    };
     
-    // Save the updated data
-    save_user_data(user_id.clone(), current_data)
+    // Save the updated data (tabs without an ID get one assigned here)
+    save_user_data(user_id.clone(), current_data, None)
         .map_err(|e| format!("Failed to save imported tab: {}", e))?;
     
-    Ok(format!("Successfully imported tab '{}': {} playlists added, {} updated", tab_name, added_count, updated_count))
+    let mut message = format!("Successfully imported tab '{}' (tab {}): {} playlists added, {} updated", tab_name, tab_action, added_count, updated_count);
+    if !missing_ids.is_empty() {
+        message.push_str(&format!(
+            ". {} missing playlist reference(s) {}: {}",
+            missing_ids.len(),
+            if prune { "removed" } else { "kept" },
+            missing_ids.join(", ")
+        ));
+    }
+    
+    Ok(message)
+}
+
+/// Create a new tab at the end of the tab list
+#[tauri::command]
+pub fn create_tab(user_id: String, name: String, playlist_ids: Option<Vec<String>>) -> Result<PlaylistTab, String> {
+    eprintln!("➕ create_tab called for user_id: {}, name: {}", user_id, name);
+    
+    update_tabs(&user_id, |tabs| {
+        let tab = PlaylistTab {
+            id: generate_tab_id(),
+            name,
+            playlist_ids: playlist_ids.unwrap_or_default(),
+        };
+        tabs.push(tab.clone());
+        Ok(tab)
+    })
+}
+
+#[tauri::command]
+pub fn rename_tab(user_id: String, tab_id: String, name: String) -> Result<(), String> {
+    eprintln!("✏️ rename_tab called for user_id: {}, tab_id: {}, name: {}", user_id, tab_id, name);
+    
+    update_tabs(&user_id, |tabs| {
+        find_tab_mut(tabs, &tab_id)?.name = name;
+        Ok(())
+    })
+}
+
+/// Reorder tabs. `tab_ids` is the new order; tabs not listed keep their relative order at the end.
+#[tauri::command]
+pub fn reorder_tabs(user_id: String, tab_ids: Vec<String>) -> Result<Vec<PlaylistTab>, String> {
+    eprintln!("🔀 reorder_tabs called for user_id: {}, {} tab IDs", user_id, tab_ids.len());
+    
+    update_tabs(&user_id, |tabs| {
+        if let Some(unknown) = tab_ids.iter().find(|id| !tabs.iter().any(|t| &t.id == *id)) {
+            return Err(format!("Tab with ID '{}' not found", unknown));
+        }
+        
+        tabs.sort_by_key(|t| tab_ids.iter().position(|id| id == &t.id).unwrap_or(usize::MAX));
+        Ok(tabs.clone())
+    })
+}
+
+#[tauri::command]
+pub fn delete_tab(user_id: String, tab_id: String) -> Result<(), String> {
+    eprintln!("🗑️ delete_tab called for user_id: {}, tab_id: {}", user_id, tab_id);
+    
+    update_tabs(&user_id, |tabs| {
+        let index = tabs.iter()
+            .position(|t| t.id == tab_id)
+            .ok_or_else(|| format!("Tab with ID '{}' not found", tab_id))?;
+        tabs.remove(index);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn add_playlist_to_tab(user_id: String, tab_id: String, playlist_id: String) -> Result<(), String> {
+    eprintln!("📌 add_playlist_to_tab called for user_id: {}, tab_id: {}, playlist_id: {}", user_id, tab_id, playlist_id);
+    
+    update_tabs(&user_id, |tabs| {
+        let tab = find_tab_mut(tabs, &tab_id)?;
+        if !tab.playlist_ids.contains(&playlist_id) {
+            tab.playlist_ids.push(playlist_id);
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn remove_playlist_from_tab(user_id: String, tab_id: String, playlist_id: String) -> Result<(), String> {
+    eprintln!("➖ remove_playlist_from_tab called for user_id: {}, tab_id: {}, playlist_id: {}", user_id, tab_id, playlist_id);
+    
+    update_tabs(&user_id, |tabs| {
+        find_tab_mut(tabs, &tab_id)?.playlist_ids.retain(|id| id != &playlist_id);
+        Ok(())
+    })
 }
 
 /// Export a single playlist as JSON string (frontend will handle file save dialog)
@@ -1187,6 +2102,9 @@ pub fn save_video_metadata_batch(metadata: Vec<serde_json::Value>) -> Result<(),
     Ok(())
 }
 
+/// Video file extensions picked up by folder scans and conversions
+pub const VIDEO_EXTENSIONS: [&str; 8] = ["mp4", "webm", "mkv", "avi", "mov", "wmv", "flv", "m4v"];
+
 /// Scan a folder for video files (.mp4, .webm) recursively
 #[tauri::command]
 pub fn scan_local_folder(folder_path: String) -> Result<Vec<serde_json::Value>, String> {
@@ -1208,7 +2126,7 @@ pub fn scan_local_folder(folder_path: String) -> Result<Vec<serde_json::Value>,
     eprintln!("✅ Path exists and is a directory");
     
     let mut video_files = Vec::new();
-    let video_extensions = ["mp4", "webm", "mkv", "avi", "mov", "wmv", "flv", "m4v"]; // Will be compared case-insensitively
+    let video_extensions = VIDEO_EXTENSIONS; // Will be compared case-insensitively
     
     fn scan_directory(dir: &PathBuf, extensions: &[&str], files: &mut Vec<serde_json::Value>) -> Result<(), String> {
         eprintln!("📂 Scanning directory: {:?}", dir);
@@ -1298,33 +2216,47 @@ pub fn scan_local_folder(folder_path: String) -> Result<Vec<serde_json::Value>,
     scan_directory(&path, &video_extensions, &mut video_files)?;
     
     eprintln!("✅ Total videos found: {}", video_files.len());
+
+    // Fill in durations etc. from the probe cache; probe the rest in the background
+    let paths: Vec<PathBuf> = video_files.iter()
+        .map(|file| PathBuf::from(file["filePath"].as_str().unwrap_or_default()))
+        .collect();
+    let mut cached = probe_cache::cached_many(&paths);
+    let mut unprobed = Vec::new();
+    for (file, file_path) in video_files.iter_mut().zip(paths) {
+        match cached.remove(&file_path) {
+            Some(info) => {
+                file["duration"] = serde_json::json!(info.duration.map(|d| d.round() as i64).unwrap_or(0));
+                file["width"] = serde_json::json!(info.width);
+                file["height"] = serde_json::json!(info.height);
+                file["videoCodec"] = serde_json::json!(info.video_codec);
+                file["audioCodec"] = serde_json::json!(info.audio_codec);
+            }
+            None => unprobed.push(file_path),
+        }
+    }
+    if !unprobed.is_empty() {
+        eprintln!("🔍 Queued {} files for background probing", unprobed.len());
+        probe_cache::queue_probe(unprobed);
+    }
+
     Ok(video_files)
 }
 
+/// Save an image (base64, optionally a data URL) as a video's thumbnail in all sizes;
+/// returns the small thumbnail's path. `source` is "uploaded" (default) or "youtube" for
+/// images fetched from YouTube for the offline cache.
 #[tauri::command]
-pub fn save_thumbnail(video_id: String, base64_data: String) -> Result<String, String> {
+pub fn save_thumbnail(video_id: String, base64_data: String, source: Option<String>) -> Result<String, String> {
     eprintln!("💾 save_thumbnail called for video_id: {}", video_id);
     
-    // Remove data URL prefix if present
-    let base64_data = base64_data
-        .strip_prefix("data:image/jpeg;base64,")
-        .or_else(|| base64_data.strip_prefix("data:image/png;base64,"))
-        .unwrap_or(&base64_data);
-    
-    // Decode base64 using the new Engine API
-    use base64::{Engine as _, engine::general_purpose};
-    let image_data = general_purpose::STANDARD
-        .decode(base64_data)
-        .map_err(|e| format!("Failed to decode base64: {}", e))?;
-    
-    // Get thumbnail path
-    let thumbnail_path = get_thumbnail_path(&video_id)?;
-    
-    // Write thumbnail file
-    let mut file = std::fs::File::create(&thumbnail_path)
-        .map_err(|e| format!("Failed to create thumbnail file: {}", e))?;
-    file.write_all(&image_data)
-        .map_err(|e| format!("Failed to write thumbnail file: {}", e))?;
+    let source = match source.as_deref() {
+        None | Some("uploaded") => "uploaded",
+        Some(youtube_thumbnails::SOURCE) => youtube_thumbnails::SOURCE,
+        Some(other) => return Err(format!("Unknown thumbnail source: {}", other)),
+    };
+    let image_data = thumbnails::decode_image_data(&base64_data)?;
+    let thumbnail_path = thumbnails::store_image(&video_id, &image_data, source)?;
     
     eprintln!("✅ Thumbnail saved to: {}", thumbnail_path.display());
     Ok(thumbnail_path.to_string_lossy().to_string())
@@ -1332,13 +2264,9 @@ pub fn save_thumbnail(video_id: String, base64_data: String) -> Result<String, S
 
 #[tauri::command]
 pub fn get_thumbnail_path_command(video_id: String) -> Result<String, String> {
-    let path = get_thumbnail_path(&video_id)?;
-    
-    // Check if file exists
-    if path.exists() {
-        Ok(path.to_string_lossy().to_string())
-    } else {
-        Err("Thumbnail file does not exist".to_string())
+    match thumbnails::find(&video_id, ThumbnailSize::Small)? {
+        Some(path) => Ok(path.to_string_lossy().to_string()),
+        None => Err("Thumbnail file does not exist".to_string()),
     }
 }
 
@@ -1356,397 +2284,72 @@ pub fn get_file_size(file_path: String) -> Result<u64, String> {
     Ok(metadata.len())
 }
 
+/// Thumbnail as a data URL with its real MIME type (`size` default: small)
 #[tauri::command]
-pub fn get_thumbnail_data_url(video_id: String) -> Result<String, String> {
-    let path = get_thumbnail_path(&video_id)?;
-    
-    // Check if file exists
-    if !path.exists() {
-        return Err("Thumbnail file does not exist".to_string());
-    }
-    
-    // Read file as bytes
-    let image_data = std::fs::read(&path)
-        .map_err(|e| format!("Failed to read thumbnail file: {}", e))?;
-    
-    // Encode as base64
-    use base64::{Engine as _, engine::general_purpose};
-    let base64_string = general_purpose::STANDARD.encode(&image_data);
-    
-    // Return as data URL
-    Ok(format!("data:image/jpeg;base64,{}", base64_string))
+pub fn get_thumbnail_data_url(video_id: String, size: Option<ThumbnailSize>) -> Result<String, String> {
+    thumbnails::get(&video_id, size.unwrap_or(ThumbnailSize::Small), true)?
+        .data_url
+        .ok_or_else(|| "Thumbnail file does not exist".to_string())
 }
 
 #[tauri::command]
 pub fn extract_video_thumbnail(video_path: String, video_id: String) -> Result<String, String> {
     eprintln!("🎬 extract_video_thumbnail called for: {}", video_path);
     
-    // Get thumbnail path
-    let thumbnail_path = get_thumbnail_path(&video_id)?;
-    
-    // Check if thumbnail already exists
-    if thumbnail_path.exists() {
-        eprintln!("✅ Thumbnail already exists: {}", thumbnail_path.display());
-        return Ok(thumbnail_path.to_string_lossy().to_string());
-    }
-    
-    // Try to extract thumbnail using FFmpeg
-    use std::process::Command;
-    
-    // First, try to extract embedded cover art (if available in MP4)
-    // For MP4 files, try to extract embedded cover art first
-    if video_path.to_lowercase().ends_with(".mp4") || video_path.to_lowercase().ends_with(".m4v") {
-        let mut extract_cover = Command::new("ffmpeg");
-        extract_cover
-            .arg("-i")
-            .arg(&video_path)
-            .arg("-map")
-            .arg("0:v:0")
-            .arg("-frames:v")
-            .arg("1")
-            .arg("-vf")
-            .arg("scale=320:180:force_original_aspect_ratio=decrease,pad=320:180:(ow-iw)/2:(oh-ih)/2")
-            .arg("-y")
-            .arg(&thumbnail_path);
-        
-        // Hide output on Windows
-        #[cfg(windows)]
-        {
-            extract_cover.stdout(std::process::Stdio::null())
-                .stderr(std::process::Stdio::null());
-        }
-        
-        let cover_result = extract_cover.output();
-        
-        if let Ok(output) = cover_result {
-            if output.status.success() && thumbnail_path.exists() {
-                eprintln!("✅ Extracted thumbnail using FFmpeg (first frame)");
-                return Ok(thumbnail_path.to_string_lossy().to_string());
-            }
-        }
-    }
-    
-    // If cover art extraction failed, try to get a frame at 5-10% of duration
-    // First, get video duration using ffprobe
-    let mut probe = Command::new("ffprobe");
-    probe
-        .arg("-v")
-        .arg("error")
-        .arg("-show_entries")
-        .arg("format=duration")
-        .arg("-of")
-        .arg("default=noprint_wrappers=1:nokey=1")
-        .arg(&video_path);
-    
-    #[cfg(windows)]
-    {
-        probe.stderr(std::process::Stdio::null());
-    }
-    
-    let duration_str = probe.output()
-        .ok()
-        .and_then(|o| String::from_utf8(o.stdout).ok())
-        .and_then(|s| s.trim().parse::<f64>().ok());
-    
-    let seek_time = if let Some(duration) = duration_str {
-        // Seek to 5-10% of duration, but at least 0.5s and at most 5s
-        let percent = duration * 0.075; // 7.5% as middle ground
-        percent.max(0.5).min(5.0)
-    } else {
-        1.0 // Default to 1 second if we can't get duration
-    };
-    
-    eprintln!("⏩ Seeking to {} seconds for thumbnail", seek_time);
-    
-    // Extract frame at specific time
-    let mut extract_frame = Command::new("ffmpeg");
-    extract_frame
-        .arg("-i")
-        .arg(&video_path)
-        .arg("-ss")
-        .arg(&format!("{:.2}", seek_time))
-        .arg("-vframes")
-        .arg("1")
-        .arg("-vf")
-        .arg("scale=320:180:force_original_aspect_ratio=decrease,pad=320:180:(ow-iw)/2:(oh-ih)/2")
-        .arg("-y")
-        .arg(&thumbnail_path);
-    
-    #[cfg(windows)]
-    {
-        extract_frame.stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null());
-    }
-    
-    let frame_result = extract_frame.output();
-    
-    if let Ok(output) = frame_result {
-        if output.status.success() && thumbnail_path.exists() {
-            eprintln!("✅ Extracted thumbnail using FFmpeg (frame at {}s)", seek_time);
-            return Ok(thumbnail_path.to_string_lossy().to_string());
-        }
-    }
-    
-    // If FFmpeg extraction failed, return error
-    Err(format!("Failed to extract thumbnail using FFmpeg. Make sure FFmpeg is installed and in PATH."))
+    thumbnails::extract_from_video(&video_path, &video_id)
+        .map(|path| path.to_string_lossy().to_string())
 }
 
+/// Move an MP4's moov box to the front (copying all streams) and replace the file. Runs the
+/// "make playable" pipeline in remux-only mode: nothing happens if the file is already
+/// faststart, and codecs are never changed.
 #[tauri::command]
 pub fn add_faststart_in_place(file_path: String) -> Result<String, String> {
     eprintln!("⚡ Adding +faststart in-place (no new file): {}", file_path);
     
-    use std::process::Command;
-    
-    let path = PathBuf::from(&file_path);
-    if !path.exists() {
-        return Err(format!("File does not exist: {}", file_path));
-    }
-    
-    let path_str = path.to_string_lossy().to_string();
-    
-    // First, check if file is valid by trying to probe it
-    eprintln!("🔍 [FASTSTART] Checking if file is valid MP4...");
-    
-    // Check file size first - if it's 0 or very small, it's definitely incomplete
-    let file_size = match std::fs::metadata(&path) {
-        Ok(meta) => meta.len(),
-        Err(e) => {
-            return Err(format!("Cannot read file metadata: {}. File may not exist or be inaccessible.", e));
-        }
-    };
-    
+    let file_size = std::fs::metadata(&file_path)
+        .map_err(|e| format!("Cannot read file metadata: {}. File may not exist or be inaccessible.", e))?
+        .len();
     if file_size == 0 {
         return Err("File is empty (0 bytes). File may be incomplete or corrupted.".to_string());
     }
     
-    if file_size < 1024 {
-        eprintln!("⚠️ [FASTSTART] File is very small ({} bytes) - may be incomplete", file_size);
-    }
-    
-    let probe_output = Command::new("ffprobe")
-        .args([
-            "-v", "error",
-            "-show_format",
-            "-show_streams",
-            &path_str,
-        ])
-        .output();
-    
-    let file_is_valid = match probe_output {
-        Ok(output) => {
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                eprintln!("⚠️ [FASTSTART] ffprobe failed: {}", stderr.trim());
-            }
-            output.status.success()
-        },
-        Err(e) => {
-            eprintln!("⚠️ [FASTSTART] ffprobe error: {}", e);
-            false
-        }
+    let policy = playable::PlayablePolicy {
+        output_path: Some(file_path.clone()),
+        copy_only: true,
+        ..Default::default()
     };
-    
-    if !file_is_valid {
-        eprintln!("⚠️ [FASTSTART] File appears corrupted or incomplete (moov atom not found)");
-        eprintln!("   File size: {} bytes ({:.2} MB)", file_size, file_size as f64 / 1024.0 / 1024.0);
-        eprintln!("   Attempting to repair file first...");
-        eprintln!("   NOTE: If this file plays in VLC/Media Player, it may just need repair.");
-        eprintln!("   If it doesn't play anywhere, the file is corrupted and needs re-downloading.");
-        
-        // Try to repair the file by remuxing it
-        // Use .mp4 extension so FFmpeg knows the format
-        let temp_repair = path.with_extension("mp4.repair");
-        let temp_repair_str = temp_repair.to_string_lossy().to_string();
-        
-        // Use raw path for Windows to handle special characters better
-        let repair_output = Command::new("ffmpeg")
-            .args([
-                "-v", "error",
-                "-err_detect", "ignore_err",
-                "-i", &path_str,
-                "-c", "copy",  // Copy streams, no re-encode
-                "-f", "mp4",  // Explicitly specify MP4 format
-                "-movflags", "+faststart",  // Add faststart during repair
-                "-y",
-                &temp_repair_str,
-            ])
-            .output();
-        
-        match repair_output {
-            Ok(output) if output.status.success() => {
-                // Replace original with repaired file
-                match std::fs::rename(&temp_repair, &path) {
-                    Ok(_) => {
-                        eprintln!("✅ [FASTSTART] File repaired and faststart added!");
-                        return Ok(file_path);
-                    }
-                    Err(e) => {
-                        let _ = std::fs::remove_file(&temp_repair);
-                        return Err(format!("File was repaired but could not replace original: {}. The repaired file was saved as: {}", e, temp_repair_str));
-                    }
-                }
-            }
-            Ok(output) => {
-                let _ = std::fs::remove_file(&temp_repair);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                return Err(format!("File appears corrupted and could not be repaired.\n\nFFmpeg error: {}\n\nThe file may be:\n- Still downloading/incomplete\n- Severely corrupted\n- Not a valid MP4 file\n\nTry re-downloading or checking the file with another tool.", stderr.trim()));
-            }
-            Err(e) => {
-                return Err(format!("Failed to attempt file repair: {}. The file appears corrupted (moov atom not found).", e));
-            }
-        }
-    }
-    
-    // File is valid, proceed with faststart
-    // NOTE: FFmpeg on Windows refuses to overwrite the same file (even with -y)
-    // So we MUST use temp file approach - write to temp, then rename
-    eprintln!("🔄 [FASTSTART] File is valid, processing with temp file approach...");
-    eprintln!("   (FFmpeg requires temp file on Windows - can't overwrite same file)");
-    
-    // Use .mp4.tmp extension so FFmpeg knows it's MP4 format
-    let temp_path = path.with_extension("mp4.tmp");
-    let temp_file = temp_path.to_string_lossy().to_string();
-    
-    // Try normal approach first
-    let temp_output = Command::new("ffmpeg")
-        .args([
-            "-v", "error",
-            "-i", &path_str,
-            "-c", "copy",
-            "-f", "mp4",  // Explicitly specify MP4 format
-            "-movflags", "+faststart",
-            "-y",
-            &temp_file,
-        ])
-        .output()
-        .map_err(|e| format!("FFmpeg temp file approach failed: {}", e))?;
-    
-    if temp_output.status.success() {
-        // Replace original with temp file (atomic operation)
-        std::fs::rename(&temp_path, &path)
-            .map_err(|e| {
-                let _ = std::fs::remove_file(&temp_path);
-                format!("Failed to replace original file: {}", e)
-            })?;
-        eprintln!("✅ [FASTSTART] Success! (moov atom moved to front)");
-        return Ok(file_path);
-    }
-    
-    // If normal approach failed, try with ignore_err (for problematic files)
-    let stderr = String::from_utf8_lossy(&temp_output.stderr);
-    let exit_code = temp_output.status.code().unwrap_or(-1);
-    eprintln!("⚠️ [FASTSTART] Normal approach failed (code {}), trying with ignore_err flag...", exit_code);
-    eprintln!("   Error: {}", stderr.trim());
-    
-    // Clean up failed temp file
-    let _ = std::fs::remove_file(&temp_path);
-    
-    let repair_output = Command::new("ffmpeg")
-        .args([
-            "-v", "error",
-            "-err_detect", "ignore_err",  // Grok's magic flag for problematic files
-            "-i", &path_str,
-            "-c", "copy",
-            "-f", "mp4",  // Explicitly specify MP4 format
-            "-movflags", "+faststart",
-            "-y",
-            &temp_file,
-        ])
-        .output()
-        .map_err(|e| format!("FFmpeg ignore_err approach failed: {}", e))?;
-    
-    if repair_output.status.success() {
-        // Replace original with temp file (atomic operation)
-        std::fs::rename(&temp_path, &path)
-            .map_err(|e| {
-                let _ = std::fs::remove_file(&temp_path);
-                format!("Failed to replace original file: {}", e)
-            })?;
-        eprintln!("✅ [FASTSTART] Success with ignore_err flag! (handled problematic subtitles/attachments)");
-        return Ok(file_path);
-    }
-    
-    // Both approaches failed
-    let _ = std::fs::remove_file(&temp_path);
-    let repair_stderr = String::from_utf8_lossy(&repair_output.stderr);
-    let repair_code = repair_output.status.code().unwrap_or(-1);
-    
-    eprintln!("❌ [FASTSTART] All strategies failed!");
-    eprintln!("   Normal temp file (code {}): {}", exit_code, stderr.trim());
-    eprintln!("   Ignore_err temp file (code {}): {}", repair_code, repair_stderr.trim());
-    
-    Err(format!(
-        "Faststart failed on all strategies.\n\
-        File: {}\n\
-        Normal approach (code {}): {}\n\
-        Ignore_err approach (code {}): {}\n\
-        \n\
-        The file may be locked by another process, or have unsupported codecs.\n\
-        Try closing any media players that might have the file open.",
-        file_path,
-        exit_code, stderr.trim(),
-        repair_code, repair_stderr.trim()
-    ))
+    playable::run(&file_path, &policy)
 }
 
+/// Re-encode an H.265/HEVC video to H.264 in place through the "make playable" pipeline
+/// (audio is only re-encoded if the browser can't decode it).
+/// `stream_selection` picks which audio streams to keep (default: FFmpeg's choice of one);
+/// `profile` names the encoding profile (default: "hevc to h264", libx264 fast / CRF 23)
 #[tauri::command]
-pub fn convert_hevc_to_h264(input_path: String) -> Result<String, String> {
+pub fn convert_hevc_to_h264(input_path: String, stream_selection: Option<StreamSelection>, profile: Option<String>) -> Result<String, String> {
     eprintln!("🔄 Converting H.265/HEVC to H.264 (browser-compatible): {}", input_path);
     
-    use std::process::Command;
-    
-    let path = PathBuf::from(&input_path);
-    if !path.exists() {
-        return Err(format!("File does not exist: {}", input_path));
-    }
-    
-    // Create temp file for conversion (will replace original after)
-    let temp_path = path.with_extension("h264.tmp");
-    let temp_file = temp_path.to_string_lossy().to_string();
-    let path_str = path.to_string_lossy().to_string();
-    
-    // Convert H.265 to H.264 with AAC audio
-    // This is slower (re-encoding required) but necessary for browser compatibility
-    let convert = Command::new("ffmpeg")
-        .args([
-            "-v", "error",
-            "-i", &path_str,
-            "-c:v", "libx264",      // Convert to H.264
-            "-preset", "fast",       // Balance speed/quality
-            "-crf", "23",            // Good quality (near-lossless)
-            "-c:a", "aac",          // Convert audio to AAC
-            "-b:a", "192k",         // Audio bitrate
-            "-f", "mp4",            // Explicitly specify MP4 format
-            "-movflags", "+faststart",  // Add faststart for streaming
-            "-y",
-            &temp_file,
-        ])
-        .output()
-        .map_err(|e| format!("Failed to execute FFmpeg: {}. Make sure FFmpeg is installed and in PATH.", e))?;
-    
-    if !convert.status.success() {
-        let _ = std::fs::remove_file(&temp_path);
-        let stderr = String::from_utf8_lossy(&convert.stderr);
-        return Err(format!("H.265 to H.264 conversion failed: {}", stderr.trim()));
+    let profile = encoding::resolve_profile_or(profile.as_deref(), encoding::HEVC_TO_H264_PROFILE)?;
+    if profile.copies_video() {
+        return Err(format!("Encoding profile '{}' copies the video stream, so it can't convert HEVC to H.264", profile.name));
     }
     
-    // Replace original with converted file
-    std::fs::rename(&temp_path, &path)
-        .map_err(|e| {
-            let _ = std::fs::remove_file(&temp_path);
-            format!("Failed to replace original file: {}", e)
-        })?;
-    
-    eprintln!("✅ Successfully converted H.265 to H.264: {}", input_path);
-    Ok(input_path)
+    let policy = playable::PlayablePolicy {
+        profile: Some(profile.name),
+        stream_selection,
+        output_path: Some(input_path.clone()),
+        ..Default::default()
+    };
+    playable::run(&input_path, &policy)
 }
 
+/// `stream_selection` picks which audio streams to keep (default: FFmpeg's choice of one);
+/// `profile` names the encoding profile (default: "web ready", H.264 baseline)
 #[tauri::command]
-pub fn make_video_web_ready(input_path: String, output_path: String) -> Result<String, String> {
+pub fn make_video_web_ready(input_path: String, output_path: String, stream_selection: Option<StreamSelection>, profile: Option<String>) -> Result<String, String> {
     eprintln!("🌐 Making video web-ready: {} -> {}", input_path, output_path);
     
-    use std::process::Command;
-    
     let input = PathBuf::from(&input_path);
     if !input.exists() {
         return Err(format!("Input file does not exist: {}", input_path));
@@ -1759,93 +2362,71 @@ pub fn make_video_web_ready(input_path: String, output_path: String) -> Result<S
             .map_err(|e| format!("Failed to create output directory: {}", e))?;
     }
     
-    // Industry-standard web-ready conversion:
-    // Try to copy video first (fast), but if codec is incompatible, convert to H.264
-    // -c:v copy: Preserve original video (zero re-encoding, fast) - but only if browser-compatible
-    // -c:v libx264 -profile:v baseline: Convert to H.264 baseline (universally supported, but slower)
-    // -c:a aac: Convert audio to AAC (universally supported by browsers)
-    // -b:a 192k: Audio bitrate (good quality, reasonable size)
+    // Industry-standard web-ready conversion, in one pass:
+    // Copy streams the browser can already decode (zero re-encoding, fast)
+    // Otherwise the profile's settings (H.264 video / AAC audio, universally supported, but slower)
     // -movflags +faststart: Move metadata to beginning (enables streaming/progressive playback)
     // This is what Plex, Jellyfin, Stremio, Cloudflare Stream use
+    let profile = encoding::resolve_profile_or(profile.as_deref(), encoding::WEB_READY_PROFILE)?;
+    let info = probe_cache::media_info(&input)?;
+    let (copy_video, copy_audio) = playable::copyable_streams(&info, &profile, false);
+    let map_args = media::stream_map_args(&input_path, stream_selection.as_ref())?;
     
-    // First, try fast mode (copy video codec) - works if original is H.264
-    let mut convert = Command::new("ffmpeg");
+    eprintln!("🎞️ Video: {} ({:?}), audio: {} ({:?})",
+        if copy_video { "copy" } else { "transcode" }, info.video_codec,
+        if copy_audio { "copy" } else { "transcode" }, info.audio_codec);
+    
+    storage::preflight(&input, &output, copy_video, copy_audio, Some(&profile))?;
+    
+    let mut convert = jobs::ffmpeg_command();
     convert
         .arg("-i")
         .arg(&input_path)
-        .arg("-c:v")
-        .arg("copy")        // Try copying video stream first (fast!)
-        .arg("-c:a")
-        .arg("aac")         // Convert audio to AAC (browser-compatible)
-        .arg("-b:a")
-        .arg("192k")        // Audio bitrate
+        .args(&map_args)
+        .args(playable::codec_args(copy_video, copy_audio, &profile))
         .arg("-movflags")
         .arg("+faststart")  // Move moov atom to beginning (enables streaming)
         .arg("-y")          // Overwrite output
         .arg(&output_path);
     
-    // Hide output on Windows
-    #[cfg(windows)]
-    {
-        convert.stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null());
-    }
+    let output = jobs::run_ffmpeg(&mut convert, &input_path)?;
     
-    let status = convert.status()
-        .map_err(|e| format!("Failed to execute FFmpeg: {}. Make sure FFmpeg is installed and in PATH.", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("FFmpeg conversion failed (exit code {:?}): {}", output.status.code(), stderr.trim()));
+    }
     
-    if !status.success() {
-        // Fast mode failed - video codec might not be copyable to MP4
-        // Try converting video to H.264 baseline (universally supported, but slower)
-        eprintln!("⚠️ Fast mode failed, trying H.264 conversion (slower but guaranteed compatibility)");
-        
-        let mut convert_h264 = Command::new("ffmpeg");
-        convert_h264
-            .arg("-i")
-            .arg(&input_path)
-            .arg("-c:v")
-            .arg("libx264")      // Convert to H.264 (browser-compatible)
-            .arg("-profile:v")
-            .arg("baseline")     // Baseline profile (maximum compatibility)
-            .arg("-preset")
-            .arg("veryfast")     // Fast encoding preset
-            .arg("-crf")
-            .arg("23")           // Quality setting
-            .arg("-c:a")
-            .arg("aac")          // Convert audio to AAC
-            .arg("-b:a")
-            .arg("192k")         // Audio bitrate
-            .arg("-movflags")
-            .arg("+faststart")   // Move moov atom to beginning
-            .arg("-y")           // Overwrite output
-            .arg(&output_path);
-        
-        #[cfg(windows)]
-        {
-            convert_h264.stdout(std::process::Stdio::null())
-                .stderr(std::process::Stdio::null());
-        }
-        
-        let status_h264 = convert_h264.status()
-            .map_err(|e| format!("Failed to execute FFmpeg H.264 conversion: {}. Make sure FFmpeg is installed and in PATH.", e))?;
-        
-        if !status_h264.success() {
-            return Err(format!("FFmpeg conversion failed (both fast and H.264 modes). Exit code: {:?}", status_h264.code()));
-        }
-        
-        eprintln!("✅ Successfully made web-ready with H.264 conversion: {}", output_path);
-        Ok(output_path)
-    } else {
-        eprintln!("✅ Successfully made web-ready (fast mode): {}", output_path);
-        Ok(output_path)
+    eprintln!("✅ Successfully made web-ready: {}", output_path);
+    Ok(output_path)
+}
+
+/// How `convert_mkv_to_mp4` treats the streams
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionMode {
+    /// Copy all streams (remux); the MP4 only plays if the codecs are browser-compatible
+    Fast,
+    /// Re-encode video and audio with the encoding profile
+    Reencode,
+    /// Copy browser-compatible streams, re-encode the rest
+    Auto,
+}
+
+impl ConversionMode {
+    /// `mode` if given, otherwise the legacy `fast_mode` flag (None = fast)
+    pub fn resolve(mode: Option<ConversionMode>, fast_mode: Option<bool>) -> Self {
+        mode.unwrap_or(if fast_mode.unwrap_or(true) { ConversionMode::Fast } else { ConversionMode::Reencode })
     }
 }
 
+/// `fast_mode`: true (default) copies all streams, false re-encodes everything; `mode`
+/// overrides it and also offers `auto` (copy browser-compatible streams, re-encode the rest).
+/// `stream_selection` picks which audio streams to keep (default: FFmpeg's choice of one);
+/// `profile` names the encoding profile used for re-encoding (default: "default")
 #[tauri::command]
-pub fn convert_mkv_to_mp4(input_path: String, output_path: String, fast_mode: Option<bool>) -> Result<String, String> {
-    eprintln!("🔄 Converting MKV to MP4: {} -> {} (fast_mode: {:?})", input_path, output_path, fast_mode);
-    
-    use std::process::Command;
+pub fn convert_mkv_to_mp4(input_path: String, output_path: String, fast_mode: Option<bool>, stream_selection: Option<StreamSelection>, profile: Option<String>, mode: Option<ConversionMode>) -> Result<String, String> {
+    let mode = ConversionMode::resolve(mode, fast_mode);
+    eprintln!("🔄 Converting MKV to MP4: {} -> {} (mode: {:?})", input_path, output_path, mode);
     
     let input = PathBuf::from(&input_path);
     if !input.exists() {
@@ -1859,143 +2440,293 @@ pub fn convert_mkv_to_mp4(input_path: String, output_path: String, fast_mode: Op
             .map_err(|e| format!("Failed to create output directory: {}", e))?;
     }
     
-    let use_fast_mode = fast_mode.unwrap_or(true); // Default to fast mode
+    let map_args = media::stream_map_args(&input_path, stream_selection.as_ref())?;
+    let profile = encoding::resolve_profile(profile.as_deref())?;
     
-    let mut convert = Command::new("ffmpeg");
-    convert.arg("-i").arg(&input_path);
+    let (copy_video, copy_audio) = match mode {
+        ConversionMode::Fast => {
+            // FAST MODE: Remux (copy streams) - nearly instant, but the MP4 only plays if
+            // the codecs are browser-compatible (H.264 + AAC)
+            eprintln!("⚡ Using FAST mode (remux/copy streams)");
+            (true, true)
+        }
+        ConversionMode::Reencode => {
+            // SLOW MODE: Full re-encode with the encoding profile
+            eprintln!("🐌 Using SLOW mode (full re-encode, profile '{}')", profile.name);
+            (false, false)
+        }
+        ConversionMode::Auto => {
+            // AUTO: copy browser-compatible streams, re-encode the rest
+            let info = probe_cache::media_info(&input)?;
+            let (copy_video, copy_audio) = playable::copyable_streams(&info, &profile, false);
+            eprintln!("🤖 Using AUTO mode (video: {}, audio: {})",
+                if copy_video { "copy" } else { "transcode" },
+                if copy_audio { "copy" } else { "transcode" });
+            (copy_video, copy_audio)
+        }
+    };
     
-    if use_fast_mode {
-        // FAST MODE: Remux (copy streams) - nearly instant if codecs are compatible
-        // This copies video/audio streams without re-encoding
-        // Works if video is H.264 and audio is AAC (most common case)
-        eprintln!("⚡ Using FAST mode (remux/copy streams)");
-        convert
-            .arg("-c:v")
-            .arg("copy")  // Copy video stream (no re-encoding)
-            .arg("-c:a")
-            .arg("copy")  // Copy audio stream (no re-encoding)
-            .arg("-movflags")
-            .arg("+faststart");  // Enable web streaming
-    } else {
-        // SLOW MODE: Full re-encode (for incompatible codecs)
-        eprintln!("🐌 Using SLOW mode (full re-encode)");
-        convert
-            .arg("-c:v")
-            .arg("libx264")  // Re-encode video to H.264
-            .arg("-c:a")
-            .arg("aac")      // Re-encode audio to AAC
-            .arg("-preset")
-            .arg("veryfast")  // Faster preset for speed
-            .arg("-crf")
-            .arg("23")       // Quality setting
-            .arg("-movflags")
-            .arg("+faststart");
-    }
-    
-    convert.arg("-y").arg(&output_path);
-    
-    // Hide output on Windows
-    #[cfg(windows)]
-    {
-        convert.stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null());
-    }
+    storage::preflight(&input, &output, copy_video, copy_audio, Some(&profile))?;
+    
+    let mut convert = jobs::ffmpeg_command();
+    convert.arg("-i").arg(&input_path).args(&map_args);
+    convert.args(playable::codec_args(copy_video, copy_audio, &profile));
+    
+    // Subtitle/data streams (e.g. SRT from MKV) can't be copied into MP4
+    convert.args(["-sn", "-dn", "-movflags", "+faststart"]);
+    
+    // Write to a temp file and rename, so a failed or cancelled run never leaves a partial
+    // output behind (folder conversions would treat it as already converted)
+    let temp_path = output.with_extension("mp4.tmp");
+    convert.args(["-f", "mp4", "-y"]).arg(&temp_path);
     
-    let status = convert.status()
-        .map_err(|e| format!("Failed to execute FFmpeg: {}. Make sure FFmpeg is installed and in PATH.", e))?;
+    let status = jobs::run_ffmpeg(&mut convert, &input_path)
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp_path);
+        })?
+        .status;
     
     if !status.success() {
+        let _ = std::fs::remove_file(&temp_path);
         // If fast mode failed, it might be due to incompatible codecs
-        // Return error with suggestion to try slow mode
-        if use_fast_mode {
-            return Err(format!("Fast remux failed (codecs may be incompatible). Try slow mode for full re-encode. Exit code: {:?}", status.code()));
+        // Return error with suggestion to try auto/slow mode
+        if mode == ConversionMode::Fast {
+            return Err(format!("Fast remux failed (codecs may be incompatible). Try auto or slow mode to re-encode. Exit code: {:?}", status.code()));
         }
         return Err(format!("FFmpeg conversion failed. Exit code: {:?}", status.code()));
     }
     
+    std::fs::rename(&temp_path, &output)
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&temp_path);
+            format!("Failed to move converted file into place: {}", e)
+        })?;
+    
     eprintln!("✅ Successfully converted: {}", output_path);
     Ok(output_path)
 }
 
+/// Options for `convert_mkv_folder_to_mp4`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct FolderConversionOptions {
+    /// Source extensions (default: the video extensions `scan_local_folder` finds, except mp4/m4v)
+    #[serde(default)]
+    pub extensions: Option<Vec<String>>,
+    /// Include subfolders (default: true); the output mirrors the folder tree
+    #[serde(default)]
+    pub recursive: Option<bool>,
+    /// Concurrent FFmpeg processes (default: 2)
+    #[serde(default)]
+    pub workers: Option<usize>,
+    /// Convert even when the output is newer than the source (default: skip those)
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Overrides `fast_mode` for every file (see `convert_mkv_to_mp4`)
+    #[serde(default)]
+    pub mode: Option<ConversionMode>,
+}
+
+/// One file of a folder conversion
+struct FolderConversionTask {
+    input: PathBuf,
+    output: PathBuf,
+}
+
+/// Find the files to convert under `folder` and their output paths (mirroring the tree under
+/// `output_dir`). The output folder itself is never scanned.
+fn collect_folder_tasks(folder: &Path, output_dir: &Path, extensions: &[String], recursive: bool) -> Result<Vec<FolderConversionTask>, String> {
+    let mut inputs = Vec::new();
+    let mut dirs = vec![folder.to_path_buf()];
+    let output_canonical = output_dir.canonicalize().ok();
+    
+    while let Some(dir) = dirs.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|e| format!("Failed to read folder {}: {}", dir.display(), e))?;
+        
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+            let path = entry.path();
+            
+            if path.is_dir() {
+                if recursive && path.canonicalize().ok() != output_canonical {
+                    dirs.push(path);
+                }
+            } else if let Some(ext) = path.extension().and_then(OsStr::to_str) {
+                if extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)) {
+                    inputs.push(path);
+                }
+            }
+        }
+    }
+    inputs.sort();
+    
+    let mut used_outputs = HashSet::new();
+    let tasks = inputs.into_iter().map(|input| {
+        let relative = input.strip_prefix(folder).unwrap_or(&input);
+        let mut output = output_dir.join(relative).with_extension("mp4");
+        // "Show.mkv" and "Show.avi" in one folder would both become "Show.mp4"
+        if !used_outputs.insert(output.clone()) {
+            let name = relative.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            output = output.with_file_name(format!("{}.mp4", name));
+            used_outputs.insert(output.clone());
+        }
+        FolderConversionTask { input, output }
+    }).collect();
+    
+    Ok(tasks)
+}
+
+/// Output folder of a folder conversion (default: a "converted" subfolder of the source)
+fn folder_output_dir(folder: &Path, output_folder: Option<&str>) -> PathBuf {
+    match output_folder {
+        Some(output) => PathBuf::from(output),
+        None => folder.join("converted"),
+    }
+}
+
+/// Extensions a folder conversion picks up (default: every video extension except MP4's)
+fn folder_extensions(options: &FolderConversionOptions) -> Vec<String> {
+    options.extensions.clone().unwrap_or_else(|| {
+        VIDEO_EXTENSIONS.iter()
+            .filter(|e| !matches!(**e, "mp4" | "m4v"))
+            .map(|e| e.to_string())
+            .collect()
+    })
+}
+
+/// Input and output paths of every file a folder conversion works on (empty if the folder
+/// can't be read)
+pub fn folder_conversion_files(folder_path: &str, output_folder: Option<&str>, options: Option<&FolderConversionOptions>) -> Vec<PathBuf> {
+    let default_options = FolderConversionOptions::default();
+    let options = options.unwrap_or(&default_options);
+    let folder = Path::new(folder_path);
+    let output_dir = folder_output_dir(folder, output_folder);
+
+    collect_folder_tasks(folder, &output_dir, &folder_extensions(options), options.recursive.unwrap_or(true))
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|task| [task.input, task.output])
+        .collect()
+}
+
+/// Whether `output` exists and is at least as new as `input`
+fn output_is_up_to_date(input: &Path, output: &Path) -> bool {
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(input), modified(output)) {
+        (Some(input), Some(output)) => output >= input,
+        _ => false,
+    }
+}
+
+/// Convert every video under a folder to MP4 with several FFmpeg processes at once.
+/// `stream_selection` and `profile` are applied to every file (an audio language works best
+/// across a folder); see `FolderConversionOptions` for extensions, recursion and concurrency.
 #[tauri::command]
-pub fn convert_mkv_folder_to_mp4(folder_path: String, output_folder: Option<String>, fast_mode: Option<bool>) -> Result<serde_json::Value, String> {
-    eprintln!("🔄 Converting all MKV files in folder: {}", folder_path);
+pub fn convert_mkv_folder_to_mp4(folder_path: String, output_folder: Option<String>, fast_mode: Option<bool>, stream_selection: Option<StreamSelection>, profile: Option<String>, options: Option<FolderConversionOptions>) -> Result<serde_json::Value, String> {
+    eprintln!("🔄 Converting videos in folder: {}", folder_path);
     
+    let options = options.unwrap_or_default();
     let folder = PathBuf::from(&folder_path);
     if !folder.exists() || !folder.is_dir() {
         return Err(format!("Folder does not exist or is not a directory: {}", folder_path));
     }
     
-    // Determine output folder
-    let output_dir = if let Some(output) = output_folder {
-        PathBuf::from(output)
-    } else {
-        // Default: create "converted" subfolder in same directory
-        folder.join("converted")
-    };
-    
+    let output_dir = folder_output_dir(&folder, output_folder.as_deref());
     std::fs::create_dir_all(&output_dir)
         .map_err(|e| format!("Failed to create output directory: {}", e))?;
     
-    let mut results = Vec::new();
-    let mut success_count = 0;
-    let mut error_count = 0;
-    
-    // Scan folder for MKV files
-    let entries = std::fs::read_dir(&folder)
-        .map_err(|e| format!("Failed to read folder: {}", e))?;
-    
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let path = entry.path();
-        
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                if ext.to_string_lossy().to_lowercase() == "mkv" {
-                    let file_name = path.file_stem()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("unknown")
-                        .to_string();
-                    
-                    let output_path = output_dir.join(format!("{}.mp4", file_name));
-                    
-                    eprintln!("📹 Converting: {} -> {}", path.display(), output_path.display());
+    let extensions = folder_extensions(&options);
+    let tasks = collect_folder_tasks(&folder, &output_dir, &extensions, options.recursive.unwrap_or(true))?;
+    let workers = options.workers.unwrap_or(2).clamp(1, 16).min(tasks.len().max(1));
+    let started = std::time::Instant::now();
+    
+    eprintln!("📹 {} files to convert with {} workers (extensions: {:?})", tasks.len(), workers, extensions);
+    
+    // Per-file progress (0-1) and results, filled in by the workers
+    let progress = std::sync::Arc::new(std::sync::Mutex::new(vec![0.0f64; tasks.len()]));
+    let results = std::sync::Mutex::new(vec![serde_json::Value::Null; tasks.len()]);
+    let next_task = std::sync::atomic::AtomicUsize::new(0);
+    let scope = jobs::current_scope();
+    
+    std::thread::scope(|threads| {
+        let handles: Vec<_> = (0..workers).map(|_| {
+            threads.spawn(|| {
+                loop {
+                    let index = next_task.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    // Stop taking files if the job was cancelled
+                    if index >= tasks.len() || scope.as_ref().map_or(false, |s| s.is_cancelled()) {
+                        break;
+                    }
+                    let task = &tasks[index];
+                    let input = task.input.to_string_lossy().to_string();
+                    let output = task.output.to_string_lossy().to_string();
                     
-                    match convert_mkv_to_mp4(
-                        path.to_string_lossy().to_string(),
-                        output_path.to_string_lossy().to_string(),
-                        fast_mode
-                    ) {
-                        Ok(output) => {
-                            results.push(serde_json::json!({
-                                "input": path.to_string_lossy().to_string(),
-                                "output": output,
-                                "status": "success"
-                            }));
-                            success_count += 1;
+                    let result = if !options.overwrite && output_is_up_to_date(&task.input, &task.output) {
+                        eprintln!("⏭️ Skipping (output is up to date): {}", input);
+                        serde_json::json!({ "input": input, "output": output, "status": "skipped" })
+                    } else {
+                        eprintln!("📹 Converting: {} -> {}", input, output);
+                        if let Some(parent) = task.output.parent() {
+                            let _ = std::fs::create_dir_all(parent);
                         }
-                        Err(e) => {
-                            results.push(serde_json::json!({
-                                "input": path.to_string_lossy().to_string(),
-                                "output": null,
-                                "status": "error",
-                                "error": e
-                            }));
-                            error_count += 1;
+                        let convert = || convert_mkv_to_mp4(input.clone(), output.clone(), fast_mode, stream_selection.clone(), profile.clone(), options.mode);
+                        let outcome = match &scope {
+                            Some(scope) => {
+                                let progress = progress.clone();
+                                scope.enter(Some(Box::new(move |fraction| {
+                                    if let Ok(mut progress) = progress.lock() {
+                                        progress[index] = fraction;
+                                    }
+                                })), convert)
+                            }
+                            None => convert(),
+                        };
+                        match outcome {
+                            Ok(output) => serde_json::json!({ "input": input, "output": output, "status": "success" }),
+                            Err(e) => serde_json::json!({ "input": input, "output": null, "status": "error", "error": e }),
                         }
+                    };
+                    
+                    if let Ok(mut progress) = progress.lock() {
+                        progress[index] = 1.0;
                     }
+                    if let Ok(mut results) = results.lock() {
+                        results[index] = result;
+                    }
+                }
+            })
+        }).collect();
+        
+        // Report overall progress while the workers run (a worker that panicked counts as
+        // finished, so this never waits for it forever)
+        if let Some(scope) = &scope {
+            while handles.iter().any(|handle| !handle.is_finished()) {
+                std::thread::sleep(std::time::Duration::from_millis(500));
+                if let Ok(progress) = progress.lock() {
+                    let done = progress.iter().sum::<f64>() / progress.len().max(1) as f64;
+                    scope.report(done, None);
                 }
             }
         }
+    });
+    
+    if jobs::is_cancelled() {
+        return Err("Cancelled".to_string());
     }
     
-    eprintln!("✅ Conversion complete: {} succeeded, {} failed", success_count, error_count);
+    let results = results.into_inner().map_err(|e| e.to_string())?;
+    let count = |status: &str| results.iter().filter(|r| r["status"] == status).count();
+    let (success_count, skipped_count, error_count) = (count("success"), count("skipped"), count("error"));
+    
+    eprintln!("✅ Conversion complete: {} succeeded, {} skipped, {} failed", success_count, skipped_count, error_count);
     
     Ok(serde_json::json!({
         "total": results.len(),
         "success": success_count,
+        "skipped": skipped_count,
         "errors": error_count,
         "output_folder": output_dir.to_string_lossy().to_string(),
+        "elapsed_seconds": started.elapsed().as_secs(),
         "results": results
     }))
 }
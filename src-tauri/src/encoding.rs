@@ -0,0 +1,233 @@
+// Named encoding profiles for conversions (quality, speed, size caps, hardware encoders).
+// Profiles are stored in the `encoding_profiles` setting; until the user saves their own,
+// the built-in ones below are used. Conversions pick a profile by name; without one they use
+// "default", or the built-in profile that reproduces their original FFmpeg arguments.
+
+use serde::{Deserialize, Serialize};
+
+const SETTINGS_KEY: &str = "encoding_profiles";
+pub const DEFAULT_PROFILE: &str = "default";
+/// Default of `make_video_web_ready`: H.264 baseline for maximum compatibility
+pub const WEB_READY_PROFILE: &str = "web ready";
+/// Default of `convert_hevc_to_h264`
+pub const HEVC_TO_H264_PROFILE: &str = "hevc to h264";
+
+/// H.264 hardware encoders we know how to drive (checked against `ffmpeg -encoders`). VAAPI
+/// isn't one: it needs a device and an upload filter chain that profiles can't express.
+const HARDWARE_ENCODERS: [&str; 4] = ["h264_nvenc", "h264_qsv", "h264_amf", "h264_videotoolbox"];
+/// Bitrate for hardware encoders when the profile has no limit (they don't support CRF)
+const DEFAULT_HW_BITRATE_KBPS: u32 = 8000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EncodingProfile {
+    pub name: String,
+    /// "libx264", a hardware encoder (e.g. "h264_nvenc") or "copy" to keep the video as-is
+    pub video_encoder: String,
+    /// libx264 quality (lower = better/larger; 18 is visually lossless, 23 is the default)
+    #[serde(default)]
+    pub crf: Option<u32>,
+    /// libx264 speed/size trade-off, e.g. "veryfast", "medium", "slow"
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// H.264 profile, e.g. "baseline", "main", "high" (None = encoder default)
+    #[serde(default)]
+    pub h264_profile: Option<String>,
+    /// Downscale taller videos to this height (keeps aspect ratio, never upscales)
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    /// Video bitrate cap (target bitrate for hardware encoders)
+    #[serde(default)]
+    pub max_video_bitrate_kbps: Option<u32>,
+    /// "aac" or "copy"
+    pub audio_encoder: String,
+    pub audio_bitrate_kbps: u32,
+    /// Downmix to this many channels (e.g. 2 for stereo)
+    #[serde(default)]
+    pub audio_channels: Option<u32>,
+}
+
+impl EncodingProfile {
+    pub fn copies_video(&self) -> bool {
+        self.video_encoder == "copy"
+    }
+
+    pub fn copies_audio(&self) -> bool {
+        self.audio_encoder == "copy"
+    }
+
+    /// FFmpeg video arguments. An unavailable hardware encoder falls back to libx264.
+    pub fn video_args(&self) -> Vec<String> {
+        if self.copies_video() {
+            return strings(&["-c:v", "copy"]);
+        }
+
+        let encoder = if self.video_encoder == "libx264" || hardware_encoders().contains(&self.video_encoder) {
+            self.video_encoder.as_str()
+        } else {
+            eprintln!("⚠️ Encoder {} is not available, using libx264", self.video_encoder);
+            "libx264"
+        };
+
+        let mut args = strings(&["-c:v", encoder]);
+        if encoder == "libx264" {
+            args.extend(strings(&["-preset", self.preset.as_deref().unwrap_or("veryfast")]));
+            args.extend(strings(&["-crf", &self.crf.unwrap_or(23).to_string()]));
+            if let Some(max) = self.max_video_bitrate_kbps {
+                args.extend(strings(&["-maxrate", &format!("{}k", max), "-bufsize", &format!("{}k", max * 2)]));
+            }
+        } else {
+            let bitrate = self.max_video_bitrate_kbps.unwrap_or(DEFAULT_HW_BITRATE_KBPS);
+            args.extend(strings(&["-b:v", &format!("{}k", bitrate), "-maxrate", &format!("{}k", bitrate)]));
+        }
+
+        if let Some(profile) = &self.h264_profile {
+            args.extend(strings(&["-profile:v", profile]));
+        }
+        if let Some(height) = self.max_height {
+            // -2 keeps the width even, which H.264 requires
+            args.extend(strings(&["-vf", &format!("scale=-2:'min(ih,{})'", height)]));
+        }
+        args.extend(strings(&["-pix_fmt", "yuv420p"]));
+        args
+    }
+
+    /// FFmpeg audio arguments
+    pub fn audio_args(&self) -> Vec<String> {
+        if self.copies_audio() {
+            return strings(&["-c:a", "copy"]);
+        }
+
+        let mut args = strings(&["-c:a", &self.audio_encoder, "-b:a", &format!("{}k", self.audio_bitrate_kbps)]);
+        if let Some(channels) = self.audio_channels {
+            args.extend(strings(&["-ac", &channels.to_string()]));
+        }
+        args
+    }
+}
+
+fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+fn profile(name: &str, video_encoder: &str, crf: Option<u32>, preset: Option<&str>, max_height: Option<u32>,
+           max_video_bitrate_kbps: Option<u32>, audio_bitrate_kbps: u32) -> EncodingProfile {
+    EncodingProfile {
+        name: name.to_string(),
+        video_encoder: video_encoder.to_string(),
+        crf,
+        preset: preset.map(|s| s.to_string()),
+        h264_profile: None,
+        max_height,
+        max_video_bitrate_kbps,
+        audio_encoder: "aac".to_string(),
+        audio_bitrate_kbps,
+        audio_channels: None,
+    }
+}
+
+/// Profiles used until the user saves their own
+pub fn builtin_profiles() -> Vec<EncodingProfile> {
+    vec![
+        profile(DEFAULT_PROFILE, "libx264", Some(23), Some("veryfast"), None, None, 192),
+        EncodingProfile {
+            h264_profile: Some("baseline".to_string()),
+            ..profile(WEB_READY_PROFILE, "libx264", Some(23), Some("veryfast"), None, None, 192)
+        },
+        profile(HEVC_TO_H264_PROFILE, "libx264", Some(23), Some("fast"), None, None, 192),
+        profile("archive quality", "libx264", Some(18), Some("slow"), None, None, 256),
+        profile("fast preview", "libx264", Some(28), Some("ultrafast"), Some(720), None, 128),
+        profile("small size", "libx264", Some(28), Some("medium"), Some(720), Some(1500), 96),
+        // Keeps the video stream and only makes the audio browser-compatible
+        profile("audio only", "copy", None, None, None, None, 192),
+    ]
+}
+
+pub fn load_profiles() -> Vec<EncodingProfile> {
+    crate::db::get_setting(SETTINGS_KEY).ok().flatten().unwrap_or_else(builtin_profiles)
+}
+
+/// Look up a profile by name (None = the default profile)
+pub fn resolve_profile(name: Option<&str>) -> Result<EncodingProfile, String> {
+    resolve_profile_or(name, DEFAULT_PROFILE)
+}
+
+/// Look up a profile by name, or `default` if None. Built-in profiles missing from a saved
+/// list (saved before they existed) still resolve to their built-in settings.
+pub fn resolve_profile_or(name: Option<&str>, default: &str) -> Result<EncodingProfile, String> {
+    let name = name.unwrap_or(default);
+    let find = |profiles: Vec<EncodingProfile>| profiles.into_iter().find(|p| p.name.eq_ignore_ascii_case(name));
+    find(load_profiles())
+        .or_else(|| find(builtin_profiles()))
+        .ok_or_else(|| format!("Encoding profile not found: {}", name))
+}
+
+/// H.264 hardware encoders this FFmpeg build includes (from the cached toolchain info; the GPU
+/// itself isn't checked)
+pub fn hardware_encoders() -> Vec<String> {
+    let toolchain = crate::toolchain::toolchain();
+    HARDWARE_ENCODERS.iter()
+        .filter(|encoder| toolchain.encoders.iter().any(|e| e == *encoder))
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn validate(profile: &EncodingProfile) -> Result<(), String> {
+    if profile.name.trim().is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+    let encoder = profile.video_encoder.as_str();
+    if encoder != "copy" && encoder != "libx264" && !hardware_encoders().iter().any(|e| e == encoder) {
+        return Err(format!(
+            "Video encoder '{}' is not available (available: copy, libx264{})",
+            encoder,
+            hardware_encoders().iter().map(|e| format!(", {}", e)).collect::<String>()
+        ));
+    }
+    if profile.audio_encoder != "copy" && profile.audio_encoder != "aac" {
+        return Err(format!("Audio encoder must be 'aac' or 'copy', got '{}'", profile.audio_encoder));
+    }
+    if profile.crf.map_or(false, |crf| crf > 51) {
+        return Err("CRF must be between 0 and 51".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_encoding_profiles() -> Result<Vec<EncodingProfile>, String> {
+    Ok(load_profiles())
+}
+
+/// Add a profile or replace the one with the same name; returns all profiles
+#[tauri::command]
+pub fn save_encoding_profile(profile: EncodingProfile) -> Result<Vec<EncodingProfile>, String> {
+    validate(&profile)?;
+    let mut profiles = load_profiles();
+    match profiles.iter_mut().find(|p| p.name.eq_ignore_ascii_case(&profile.name)) {
+        Some(existing) => *existing = profile,
+        None => profiles.push(profile),
+    }
+    crate::db::set_setting(SETTINGS_KEY, &profiles)?;
+    Ok(profiles)
+}
+
+/// Delete a profile (the default profile can be edited but not deleted); returns all profiles
+#[tauri::command]
+pub fn delete_encoding_profile(name: String) -> Result<Vec<EncodingProfile>, String> {
+    if name.eq_ignore_ascii_case(DEFAULT_PROFILE) {
+        return Err("The default profile cannot be deleted".to_string());
+    }
+    let mut profiles = load_profiles();
+    let before = profiles.len();
+    profiles.retain(|p| !p.name.eq_ignore_ascii_case(&name));
+    if profiles.len() == before {
+        return Err(format!("Encoding profile not found: {}", name));
+    }
+    crate::db::set_setting(SETTINGS_KEY, &profiles)?;
+    Ok(profiles)
+}
+
+/// H.264 hardware encoders usable in profiles
+#[tauri::command]
+pub fn get_hardware_encoders() -> Result<Vec<String>, String> {
+    Ok(hardware_encoders())
+}
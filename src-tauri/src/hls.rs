@@ -0,0 +1,265 @@
+// HLS packaging for large local videos. Multi-GB files stall when the webview streams them
+// as one progressive download; as HLS the player only fetches the few-second segments it
+// needs, so seeking is cheap.
+//
+// FFmpeg segments a file into <data dir>/hls_cache/<key>/ (index.m3u8 + init.mp4 + .m4s
+// segments), either ahead of time (`prepare_hls`) or on the first playlist request.
+// The cache is evicted least-recently-played first once it exceeds the configured size.
+
+use crate::{cache_dir, media};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Request, Response};
+
+const SETTINGS_KEY: &str = "hls_cache";
+const PLAYLIST: &str = "index.m3u8";
+const COMPLETE_MARKER: &str = ".complete";
+const FAILED_MARKER: &str = ".failed";
+const SEGMENT_SECONDS: u32 = 6;
+/// How long a playlist request waits for FFmpeg to write the first segments
+const PLAYLIST_WAIT: Duration = Duration::from_secs(60);
+
+/// Cache keys currently being packaged
+static IN_PROGRESS: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn in_progress() -> &'static Mutex<HashSet<String>> {
+    IN_PROGRESS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HlsCacheSettings {
+    /// Total cache size before least-recently-played entries are evicted
+    pub max_bytes: u64,
+}
+
+impl Default for HlsCacheSettings {
+    fn default() -> Self {
+        HlsCacheSettings { max_bytes: 10 * 1024 * 1024 * 1024 }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct HlsStatus {
+    pub ready: bool,
+    pub packaging: bool,
+    pub failed: Option<String>,
+    pub cache_dir: String,
+}
+
+fn load_settings() -> HlsCacheSettings {
+    crate::db::get_setting(SETTINGS_KEY).ok().flatten().unwrap_or_default()
+}
+
+fn cache_root() -> Result<PathBuf, String> {
+    crate::db::get_cache_dir("hls_cache")
+}
+
+/// Cache key from path + size + mtime, so an edited file gets re-packaged
+fn cache_key(path: &Path) -> Result<String, String> {
+    media::file_cache_key(path)
+}
+
+/// Status of a file's HLS package
+pub fn status(path: &Path) -> Result<HlsStatus, String> {
+    let key = cache_key(path)?;
+    let dir = cache_root()?.join(&key);
+    let packaging = in_progress().lock().map(|p| p.contains(&key)).unwrap_or(false);
+
+    Ok(HlsStatus {
+        ready: dir.join(COMPLETE_MARKER).exists(),
+        packaging,
+        failed: std::fs::read_to_string(dir.join(FAILED_MARKER)).ok(),
+        cache_dir: dir.to_string_lossy().to_string(),
+    })
+}
+
+/// Start packaging in the background unless it's already done or running. Returns the cache dir.
+pub fn ensure_packaged(path: &Path) -> Result<PathBuf, String> {
+    let key = cache_key(path)?;
+    let dir = cache_root()?.join(&key);
+
+    if dir.join(COMPLETE_MARKER).exists() {
+        return Ok(dir);
+    }
+
+    // Don't retry a failed package on every request; `prepare_hls` with retry clears this
+    if let Ok(error) = std::fs::read_to_string(dir.join(FAILED_MARKER)) {
+        return Err(error);
+    }
+
+    {
+        let mut running = in_progress().lock().map_err(|e| e.to_string())?;
+        if running.contains(&key) {
+            return Ok(dir);
+        }
+        running.insert(key.clone());
+    }
+
+    // Start from scratch (a previous run may have been interrupted or failed)
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Could not create HLS cache directory: {}", e))?;
+
+    let source = path.to_path_buf();
+    let target = dir.clone();
+    thread::spawn(move || {
+        match package(&source, &target) {
+            Ok(()) => {
+                let _ = std::fs::write(target.join(COMPLETE_MARKER), b"");
+                eprintln!("✅ HLS package ready: {}", source.display());
+            }
+            Err(e) => {
+                let _ = std::fs::write(target.join(FAILED_MARKER), e.as_bytes());
+                eprintln!("❌ HLS packaging failed for {}: {}", source.display(), e);
+            }
+        }
+
+        if let Ok(mut running) = in_progress().lock() {
+            running.remove(&key);
+        }
+
+        if let Err(e) = evict(load_settings().max_bytes) {
+            eprintln!("⚠️ HLS cache eviction failed: {}", e);
+        }
+    });
+
+    Ok(dir)
+}
+
+/// Forget a failed packaging attempt so the next request tries again
+pub fn clear_failure(path: &Path) -> Result<(), String> {
+    let dir = cache_root()?.join(cache_key(path)?);
+    if dir.join(FAILED_MARKER).exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear HLS cache entry: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Run FFmpeg to segment a file (blocking)
+fn package(source: &Path, dir: &Path) -> Result<(), String> {
+    let source_str = source.to_string_lossy().to_string();
+    let plan = crate::streaming::plan_stream(&source_str, None)?;
+    eprintln!("📦 Packaging HLS for {} (video: {}, audio: {})",
+        source_str,
+        if plan.copy_video { "copy" } else { "transcode" },
+        if plan.copy_audio { "copy" } else { "transcode" });
+
+    let mut command = crate::toolchain::ffmpeg();
+    command
+        .args(["-v", "error", "-i", &source_str])
+        .args(["-map", "0:v:0?", "-map", "0:a:0?"]);
+
+    if plan.copy_video {
+        command.args(["-c:v", "copy"]);
+    } else {
+        command.args(["-c:v", "libx264", "-preset", "veryfast", "-crf", "23", "-pix_fmt", "yuv420p"]);
+    }
+
+    if plan.copy_audio {
+        command.args(["-c:a", "copy"]);
+    } else {
+        command.args(["-c:a", "aac", "-b:a", "192k"]);
+    }
+
+    // "event" playlists can be played while FFmpeg is still appending segments
+    let output = command
+        .args([
+            "-f", "hls",
+            "-hls_time", &SEGMENT_SECONDS.to_string(),
+            "-hls_playlist_type", "event",
+            "-hls_segment_type", "fmp4",
+            "-hls_fmp4_init_filename", "init.mp4",
+            "-hls_segment_filename",
+        ])
+        .arg(dir.join("segment_%05d.m4s"))
+        .arg("-y")
+        .arg(dir.join(PLAYLIST))
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| crate::toolchain::spawn_error("FFmpeg", &e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("FFmpeg HLS packaging failed: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+/// Serve /hls/<token>/<file>. Requesting the playlist starts packaging if needed and waits
+/// until FFmpeg has written it.
+pub fn serve(request: Request, source: &Path, file: &str) {
+    // Only plain file names from our own package (no separators or `..`)
+    let valid_name = !file.is_empty()
+        && !file.starts_with('.')
+        && file.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+    if !valid_name {
+        let _ = request.respond(Response::from_string("Bad path").with_status_code(400));
+        return;
+    }
+
+    let dir = match ensure_packaged(source) {
+        Ok(dir) => dir,
+        Err(e) => {
+            let _ = request.respond(Response::from_string(e).with_status_code(500));
+            return;
+        }
+    };
+
+    let target = dir.join(file);
+
+    if file == PLAYLIST {
+        let started = Instant::now();
+        while !target.exists() && !dir.join(FAILED_MARKER).exists() && started.elapsed() < PLAYLIST_WAIT {
+            thread::sleep(Duration::from_millis(200));
+        }
+        cache_dir::mark_accessed(&dir);
+    } else {
+        // Keep a package that is still being played from looking idle to `evict`
+        cache_dir::touch_access(&dir);
+    }
+
+    if !target.exists() {
+        let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+        return;
+    }
+
+    crate::video_server::serve_file(request, &target.to_string_lossy());
+}
+
+/// Delete least-recently-played packages until the cache fits in `max_bytes`.
+/// Packages being written are never evicted. Returns the number of bytes freed.
+pub fn evict(max_bytes: u64) -> Result<u64, String> {
+    let root = cache_root()?;
+    let running = in_progress().lock().map_err(|e| e.to_string())?.clone();
+    let key = |dir: &Path| dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+    let evicted = cache_dir::evict(&root, max_bytes, |dir| running.contains(&key(dir)))
+        .map_err(|e| format!("Failed to read HLS cache: {}", e))?;
+    for (dir, size) in &evicted {
+        eprintln!("🧹 Evicted HLS package {} ({} bytes)", key(dir), size);
+    }
+    Ok(evicted.iter().map(|(_, size)| size).sum())
+}
+
+#[tauri::command]
+pub fn get_hls_cache_settings() -> Result<HlsCacheSettings, String> {
+    Ok(load_settings())
+}
+
+/// Save the cache size limit and evict down to it right away
+#[tauri::command]
+pub fn set_hls_cache_settings(settings: HlsCacheSettings) -> Result<u64, String> {
+    crate::db::set_setting(SETTINGS_KEY, &settings)?;
+    evict(settings.max_bytes)
+}
+
+/// Evict down to the configured limit (or `max_bytes` if given, e.g. 0 to clear the cache)
+#[tauri::command]
+pub fn evict_hls_cache(max_bytes: Option<u64>) -> Result<u64, String> {
+    evict(max_bytes.unwrap_or_else(|| load_settings().max_bytes))
+}
@@ -0,0 +1,717 @@
+// Background job queue for FFmpeg conversions. `enqueue_job` returns a job ID right away and
+// a worker thread runs the conversion; FFmpeg's `-progress` output is turned into percent/ETA
+// and every change is sent to the frontend as a "job-updated" event.
+//
+// Conversions run one at a time. Light jobs (thumbnails, seek previews, YouTube fetches) wait
+// in a queue of their own with its own worker, so they never sit behind a long conversion.
+//
+// Conversion code builds FFmpeg commands with `ffmpeg_command` and runs them through
+// `run_ffmpeg`, which reports progress and honours cancellation when called from a job (and
+// behaves like `Command::output` otherwise).
+//
+// Jobs are stored in the `conversion_jobs` table. At startup, temp files left by a previous
+// run are deleted and jobs that were queued or running when the app closed are queued again.
+
+use crate::db::{ConversionMode, FolderConversionOptions};
+use crate::media::StreamSelection;
+use crate::playable::PlayablePolicy;
+use crate::thumbnails::ThumbnailBatchItem;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+
+const JOB_EVENT: &str = "job-updated";
+/// Finished jobs are kept in the history this long
+const HISTORY_DAYS: u64 = 30;
+/// Temp files the conversions write next to their files (`Path::with_extension`)
+const TEMP_EXTENSIONS: [&str; 3] = ["mp4.tmp", "h264.tmp", "mp4.repair"];
+
+/// What a job does (same arguments as the corresponding synchronous command)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobRequest {
+    ConvertMkvToMp4 {
+        input_path: String,
+        output_path: String,
+        fast_mode: Option<bool>,
+        stream_selection: Option<StreamSelection>,
+        #[serde(default)]
+        profile: Option<String>,
+        #[serde(default)]
+        mode: Option<ConversionMode>,
+    },
+    ConvertMkvFolderToMp4 {
+        folder_path: String,
+        output_folder: Option<String>,
+        fast_mode: Option<bool>,
+        stream_selection: Option<StreamSelection>,
+        #[serde(default)]
+        profile: Option<String>,
+        #[serde(default)]
+        options: Option<FolderConversionOptions>,
+    },
+    ConvertHevcToH264 {
+        input_path: String,
+        stream_selection: Option<StreamSelection>,
+        #[serde(default)]
+        profile: Option<String>,
+    },
+    MakeVideoWebReady {
+        input_path: String,
+        output_path: String,
+        stream_selection: Option<StreamSelection>,
+        #[serde(default)]
+        profile: Option<String>,
+    },
+    AddFaststartInPlace {
+        file_path: String,
+    },
+    /// Minimal conversion from `ensure_playable` (re-planned when the job starts)
+    EnsurePlayable {
+        path: String,
+        policy: PlayablePolicy,
+    },
+    /// Thumbnails for many videos (`extract_thumbnails_batch`)
+    ExtractThumbnails {
+        items: Vec<ThumbnailBatchItem>,
+        #[serde(default)]
+        workers: Option<usize>,
+    },
+    /// YouTube thumbnails for the offline cache (`warm_thumbnail_cache`)
+    FetchYouTubeThumbnails {
+        video_ids: Vec<String>,
+    },
+    /// Seek-bar preview sprites (`prepare_seek_previews`)
+    GeneratePreviews {
+        path: String,
+        #[serde(default)]
+        interval_seconds: Option<u32>,
+        #[serde(default)]
+        tile_width: Option<u32>,
+    },
+}
+
+/// Which queue (and worker) a job runs in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lane {
+    /// CPU/disk heavy conversions, one at a time
+    Conversion,
+    /// Short jobs the UI is waiting on
+    Light,
+}
+
+impl JobRequest {
+    fn lane(&self) -> Lane {
+        match self {
+            JobRequest::ExtractThumbnails { .. }
+            | JobRequest::FetchYouTubeThumbnails { .. }
+            | JobRequest::GeneratePreviews { .. } => Lane::Light,
+            _ => Lane::Conversion,
+        }
+    }
+
+    /// Temp files this job may have written: each file it reads or writes with each temp
+    /// extension (folder conversions: every input and output in the folder)
+    fn temp_files(&self) -> Vec<PathBuf> {
+        let files: Vec<PathBuf> = match self {
+            JobRequest::ConvertMkvToMp4 { input_path, output_path, .. }
+            | JobRequest::MakeVideoWebReady { input_path, output_path, .. } => vec![input_path.into(), output_path.into()],
+            JobRequest::ConvertMkvFolderToMp4 { folder_path, output_folder, options, .. } => {
+                crate::db::folder_conversion_files(folder_path, output_folder.as_deref(), options.as_ref())
+            }
+            JobRequest::ConvertHevcToH264 { input_path, .. } => vec![input_path.into()],
+            JobRequest::AddFaststartInPlace { file_path } => vec![file_path.into()],
+            JobRequest::EnsurePlayable { path, policy } => {
+                let mut files = vec![PathBuf::from(path)];
+                files.extend(policy.output_path.as_deref().map(PathBuf::from));
+                files
+            }
+            // Thumbnails are written to the data folder, never next to the videos
+            JobRequest::ExtractThumbnails { .. }
+            | JobRequest::FetchYouTubeThumbnails { .. }
+            | JobRequest::GeneratePreviews { .. } => Vec::new(),
+        };
+        files.iter()
+            .flat_map(|file| TEMP_EXTENSIONS.iter().map(move |ext| file.with_extension(ext)))
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "running",
+            JobState::Completed => "completed",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<JobState> {
+        match value {
+            "queued" => Some(JobState::Queued),
+            "running" => Some(JobState::Running),
+            "completed" => Some(JobState::Completed),
+            "failed" => Some(JobState::Failed),
+            "cancelled" => Some(JobState::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Job {
+    pub id: String,
+    pub request: JobRequest,
+    pub state: JobState,
+    /// 0-100, None until FFmpeg reports progress
+    pub percent: Option<f64>,
+    /// Estimated seconds remaining
+    pub eta_seconds: Option<f64>,
+    /// File being converted (folder jobs)
+    pub current_file: Option<String>,
+    /// The command's result (output path or folder report)
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+    /// Job this one retries
+    pub retry_of: Option<String>,
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+}
+
+#[derive(Default)]
+struct JobStore {
+    jobs: Vec<Job>,
+    conversion_queue: VecDeque<String>,
+    light_queue: VecDeque<String>,
+    cancel_flags: HashMap<String, Arc<AtomicBool>>,
+}
+
+impl JobStore {
+    fn queue(&mut self, lane: Lane) -> &mut VecDeque<String> {
+        match lane {
+            Lane::Conversion => &mut self.conversion_queue,
+            Lane::Light => &mut self.light_queue,
+        }
+    }
+}
+
+static STORE: OnceLock<(Mutex<JobStore>, Condvar)> = OnceLock::new();
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+fn store() -> &'static (Mutex<JobStore>, Condvar) {
+    STORE.get_or_init(|| {
+        for lane in [Lane::Conversion, Lane::Light] {
+            thread::spawn(move || worker(lane));
+        }
+        (Mutex::new(JobStore::default()), Condvar::new())
+    })
+}
+
+/// Give the job queue the app handle it emits progress events through, load the job history
+/// and restart jobs interrupted by the last exit
+pub fn init(app: AppHandle) {
+    let _ = APP.set(app);
+    let started = SystemTime::now();
+
+    let cutoff = now().saturating_sub(HISTORY_DAYS * 24 * 60 * 60);
+    if let Err(e) = crate::db::delete_finished_jobs(cutoff) {
+        eprintln!("⚠️ Could not prune job history: {}", e);
+    }
+
+    let jobs = match crate::db::load_jobs() {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            eprintln!("⚠️ Could not load job history: {}", e);
+            Vec::new()
+        }
+    };
+    let interrupted: Vec<Job> = jobs.iter()
+        .filter(|j| matches!(j.state, JobState::Queued | JobState::Running))
+        .cloned()
+        .collect();
+
+    if let Ok(mut store) = store().0.lock() {
+        store.jobs = jobs;
+    }
+
+    // Clean up before re-queueing, so restarted jobs don't race the cleanup
+    thread::spawn(move || {
+        clean_temp_files(&interrupted, started);
+
+        for job in interrupted {
+            eprintln!("🔁 Re-queueing job {} (interrupted while {})", job.id, job.state.as_str());
+            update_job(&job.id, |job| {
+                job.state = JobState::Queued;
+                job.percent = None;
+                job.eta_seconds = None;
+                job.current_file = None;
+                job.started_at = None;
+            });
+            let (lock, ready) = store();
+            if let Ok(mut store) = lock.lock() {
+                store.queue(job.request.lane()).push_back(job.id);
+            }
+            // Both workers wait on the same condvar, so wake both
+            ready.notify_all();
+        }
+    });
+}
+
+/// Delete conversion temp files older than `started` that interrupted jobs left next to their
+/// own files (when the app exited mid-conversion). Only the temp names those jobs could have
+/// written are checked; nothing else in their folders is touched.
+fn clean_temp_files(interrupted: &[Job], started: SystemTime) {
+    let mut removed = 0;
+    for job in interrupted {
+        for path in job.request.temp_files() {
+            let is_stale = std::fs::metadata(&path)
+                .map_or(false, |m| m.is_file() && m.modified().map_or(false, |m| m < started));
+            if is_stale && std::fs::remove_file(&path).is_ok() {
+                eprintln!("🧹 Removed orphaned temp file {}", path.display());
+                removed += 1;
+            }
+        }
+    }
+
+    if removed > 0 {
+        eprintln!("🧹 Removed {} orphaned conversion temp files", removed);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn generate_job_id() -> String {
+    use std::sync::atomic::AtomicU64;
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("job_{}_{}", millis, n)
+}
+
+fn emit(job: &Job) {
+    if let Some(app) = APP.get() {
+        let _ = app.emit(JOB_EVENT, job.clone());
+    }
+}
+
+/// Apply a change to a job, emit the updated job and store it if its state changed
+/// (progress updates are only kept in memory)
+fn update_job(id: &str, f: impl FnOnce(&mut Job)) {
+    let (lock, _) = store();
+    let updated = match lock.lock() {
+        Ok(mut store) => store.jobs.iter_mut().find(|j| j.id == id).map(|job| {
+            let state = job.state;
+            f(job);
+            // Saved under the lock so concurrent state changes reach the database in order
+            if job.state != state {
+                persist(job);
+            }
+            job.clone()
+        }),
+        Err(_) => None,
+    };
+    if let Some(job) = updated {
+        emit(&job);
+    }
+}
+
+fn persist(job: &Job) {
+    if let Err(e) = crate::db::save_job(job) {
+        eprintln!("⚠️ Could not save job {}: {}", job.id, e);
+    }
+}
+
+/// Add a job to the queue and return its ID
+pub fn enqueue(request: JobRequest, retry_of: Option<String>) -> Result<String, String> {
+    let job = Job {
+        id: generate_job_id(),
+        request,
+        state: JobState::Queued,
+        percent: None,
+        eta_seconds: None,
+        current_file: None,
+        result: None,
+        error: None,
+        retry_of,
+        created_at: now(),
+        started_at: None,
+        finished_at: None,
+    };
+
+    crate::db::save_job(&job)?;
+
+    let (lock, ready) = store();
+    {
+        let mut store = lock.lock().map_err(|e| e.to_string())?;
+        store.queue(job.request.lane()).push_back(job.id.clone());
+        store.jobs.push(job.clone());
+    }
+    ready.notify_all();
+
+    eprintln!("📋 Queued job {}", job.id);
+    emit(&job);
+    Ok(job.id)
+}
+
+/// Run the jobs of one lane, in order
+fn worker(lane: Lane) {
+    loop {
+        let (id, request, cancel, started) = {
+            let (lock, ready) = store();
+            let mut store = match lock.lock() {
+                Ok(store) => store,
+                Err(_) => return,
+            };
+            while store.queue(lane).is_empty() {
+                store = match ready.wait(store) {
+                    Ok(store) => store,
+                    Err(_) => return,
+                };
+            }
+            let id = store.queue(lane).pop_front().unwrap();
+            // Marked running under the same lock as the pop, so `cancel_job` either still
+            // finds the job queued or finds it running with its cancel flag set up
+            let started = match store.jobs.iter_mut().find(|j| j.id == id) {
+                Some(job) => {
+                    job.state = JobState::Running;
+                    job.started_at = Some(now());
+                    persist(job);
+                    job.clone()
+                }
+                None => continue,
+            };
+            let cancel = Arc::new(AtomicBool::new(false));
+            store.cancel_flags.insert(id.clone(), cancel.clone());
+            (id, started.request.clone(), cancel, started)
+        };
+
+        emit(&started);
+        eprintln!("▶️ Running job {}", id);
+
+        let scope = JobScope { id: id.clone(), cancel: cancel.clone(), started: Instant::now() };
+        let result = scope.enter(None, || run_request(request));
+
+        let cancelled = cancel.load(Ordering::SeqCst);
+        update_job(&id, |job| {
+            job.finished_at = Some(now());
+            job.eta_seconds = None;
+            match (&result, cancelled) {
+                (_, true) => job.state = JobState::Cancelled,
+                (Ok(value), false) => {
+                    job.state = JobState::Completed;
+                    job.percent = Some(100.0);
+                    job.result = Some(value.clone());
+                }
+                (Err(e), false) => {
+                    job.state = JobState::Failed;
+                    job.error = Some(e.clone());
+                }
+            }
+        });
+
+        if let Ok(mut store) = store().0.lock() {
+            store.cancel_flags.remove(&id);
+        }
+        match result {
+            Ok(_) => eprintln!("✅ Job {} finished", id),
+            Err(e) => eprintln!("❌ Job {} failed: {}", id, e),
+        }
+    }
+}
+
+fn run_request(request: JobRequest) -> Result<serde_json::Value, String> {
+    use crate::db;
+
+    match request {
+        JobRequest::ConvertMkvToMp4 { input_path, output_path, fast_mode, stream_selection, profile, mode } => {
+            db::convert_mkv_to_mp4(input_path, output_path, fast_mode, stream_selection, profile, mode).map(serde_json::Value::from)
+        }
+        JobRequest::ConvertMkvFolderToMp4 { folder_path, output_folder, fast_mode, stream_selection, profile, options } => {
+            db::convert_mkv_folder_to_mp4(folder_path, output_folder, fast_mode, stream_selection, profile, options)
+        }
+        JobRequest::ConvertHevcToH264 { input_path, stream_selection, profile } => {
+            db::convert_hevc_to_h264(input_path, stream_selection, profile).map(serde_json::Value::from)
+        }
+        JobRequest::MakeVideoWebReady { input_path, output_path, stream_selection, profile } => {
+            db::make_video_web_ready(input_path, output_path, stream_selection, profile).map(serde_json::Value::from)
+        }
+        JobRequest::AddFaststartInPlace { file_path } => {
+            db::add_faststart_in_place(file_path).map(serde_json::Value::from)
+        }
+        JobRequest::EnsurePlayable { path, policy } => {
+            crate::playable::run(&path, &policy).map(serde_json::Value::from)
+        }
+        JobRequest::ExtractThumbnails { items, workers } => {
+            crate::thumbnails::extract_batch(&items, workers)
+        }
+        JobRequest::FetchYouTubeThumbnails { video_ids } => {
+            crate::youtube_thumbnails::fetch_all(&video_ids, &crate::youtube_thumbnails::FfmpegFetcher)
+        }
+        JobRequest::GeneratePreviews { path, interval_seconds, tile_width } => {
+            crate::previews::run(Path::new(&path), interval_seconds, tile_width)
+        }
+    }
+}
+
+/// A running job, for reporting progress and checking cancellation from any thread
+/// (e.g. the workers of a parallel folder conversion)
+#[derive(Clone)]
+pub struct JobScope {
+    id: String,
+    cancel: Arc<AtomicBool>,
+    started: Instant,
+}
+
+/// The job running on this thread
+struct JobContext {
+    scope: JobScope,
+    /// Receives FFmpeg progress (0-1 of the current run) instead of the job itself
+    on_progress: Option<Box<dyn Fn(f64)>>,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<JobContext>> = const { RefCell::new(None) };
+}
+
+/// The job running on this thread, if any
+pub fn current_scope() -> Option<JobScope> {
+    CURRENT.with(|current| current.borrow().as_ref().map(|ctx| ctx.scope.clone()))
+}
+
+/// Whether the job running on this thread has been cancelled (false outside jobs)
+pub fn is_cancelled() -> bool {
+    current_scope().map_or(false, |scope| scope.is_cancelled())
+}
+
+impl JobScope {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    /// Run `f` on this thread as part of the job: FFmpeg runs in it can be cancelled, and
+    /// their progress goes to `on_progress` (or to the job itself if None)
+    pub fn enter<T>(&self, on_progress: Option<Box<dyn Fn(f64)>>, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.with(|current| {
+            current.borrow_mut().replace(JobContext { scope: self.clone(), on_progress })
+        });
+        let result = f();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+        result
+    }
+
+    /// Set the job's overall progress (0-1) and estimate the time remaining
+    pub fn report(&self, fraction: f64, current_file: Option<String>) {
+        let overall = fraction.clamp(0.0, 1.0);
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let eta = if overall > 0.01 { Some(elapsed * (1.0 - overall) / overall) } else { None };
+        update_job(&self.id, |job| {
+            job.percent = Some(overall * 100.0);
+            job.eta_seconds = eta;
+            if current_file.is_some() {
+                job.current_file = current_file;
+            }
+        });
+    }
+}
+
+/// Record FFmpeg's position (fraction 0-1 of the current run)
+fn report_progress(fraction: f64) {
+    let scope = CURRENT.with(|current| {
+        let current = current.borrow();
+        match current.as_ref() {
+            Some(JobContext { on_progress: Some(callback), .. }) => {
+                callback(fraction.clamp(0.0, 1.0));
+                None
+            }
+            Some(ctx) => Some(ctx.scope.clone()),
+            None => None,
+        }
+    });
+    if let Some(scope) = scope {
+        scope.report(fraction, None);
+    }
+}
+
+fn in_job() -> bool {
+    CURRENT.with(|current| current.borrow().is_some())
+}
+
+/// An FFmpeg `Command` for `run_ffmpeg`. Inside a job it starts with `-progress pipe:1`
+/// (a global option, so it has to come before the caller's arguments).
+pub fn ffmpeg_command() -> Command {
+    let mut command = crate::toolchain::ffmpeg();
+    if in_job() {
+        command.args(["-progress", "pipe:1", "-nostats"]);
+    }
+    command
+}
+
+/// Run an FFmpeg command to completion. Inside a job, the process is killed if the job is
+/// cancelled, and for commands from `ffmpeg_command` its progress is reported against
+/// `input`'s duration (stdout is read for it, so it isn't returned).
+pub fn run_ffmpeg(command: &mut Command, input: &str) -> Result<Output, String> {
+    let spawn_error = |e: std::io::Error| crate::toolchain::spawn_error("FFmpeg", &e);
+
+    if !in_job() {
+        return command.stdin(Stdio::null()).output().map_err(spawn_error);
+    }
+
+    let duration = crate::probe_cache::media_info(Path::new(input)).ok().and_then(|info| info.duration);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn_error)?;
+
+    // Drain stderr on its own thread so FFmpeg never blocks on a full pipe
+    let mut stderr = child.stderr.take();
+    let stderr_reader = thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(stderr) = stderr.as_mut() {
+            let _ = stderr.read_to_end(&mut buffer);
+        }
+        buffer
+    });
+
+    // "out_time_us=<microseconds>" lines, one block every ~0.5s
+    let (sender, receiver) = mpsc::channel::<f64>();
+    let stdout = child.stdout.take();
+    thread::spawn(move || {
+        if let Some(stdout) = stdout {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let value = line.strip_prefix("out_time_us=").or_else(|| line.strip_prefix("out_time_ms="));
+                if let Some(seconds) = value.and_then(|v| v.trim().parse::<f64>().ok()).map(|us| us / 1_000_000.0) {
+                    if sender.send(seconds).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let status = loop {
+        if is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            let _ = stderr_reader.join();
+            return Err("Cancelled".to_string());
+        }
+
+        match receiver.recv_timeout(Duration::from_millis(250)) {
+            Ok(position) => {
+                if let Some(duration) = duration.filter(|d| *d > 0.0) {
+                    report_progress(position / duration);
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) | Err(mpsc::RecvTimeoutError::Disconnected) => {}
+        }
+
+        if let Some(status) = child.try_wait().map_err(|e| format!("Failed to wait for FFmpeg: {}", e))? {
+            break status;
+        }
+    };
+
+    Ok(Output {
+        status,
+        stdout: Vec::new(),
+        stderr: stderr_reader.join().unwrap_or_default(),
+    })
+}
+
+/// Whether a job is queued or running
+pub fn is_active(job_id: &str) -> bool {
+    store().0.lock()
+        .map(|store| store.jobs.iter().any(|j| j.id == job_id && matches!(j.state, JobState::Queued | JobState::Running)))
+        .unwrap_or(false)
+}
+
+/// Queue a conversion and return its job ID; progress arrives as "job-updated" events
+#[tauri::command]
+pub fn enqueue_job(request: JobRequest) -> Result<String, String> {
+    enqueue(request, None)
+}
+
+/// All jobs (including the history from earlier runs), oldest first
+#[tauri::command]
+pub fn list_jobs() -> Result<Vec<Job>, String> {
+    let store = store().0.lock().map_err(|e| e.to_string())?;
+    Ok(store.jobs.clone())
+}
+
+/// Cancel a queued or running job (running FFmpeg processes are killed)
+#[tauri::command]
+pub fn cancel_job(job_id: String) -> Result<(), String> {
+    let queued_job = {
+        let mut store = store().0.lock().map_err(|e| e.to_string())?;
+        let (state, lane) = store.jobs.iter().find(|j| j.id == job_id)
+            .map(|j| (j.state, j.request.lane()))
+            .ok_or_else(|| format!("Job not found: {}", job_id))?;
+
+        match state {
+            JobState::Queued => {
+                store.queue(lane).retain(|id| *id != job_id);
+                true
+            }
+            JobState::Running => {
+                if let Some(flag) = store.cancel_flags.get(&job_id) {
+                    flag.store(true, Ordering::SeqCst);
+                }
+                false
+            }
+            _ => return Err(format!("Job {} has already finished", job_id)),
+        }
+    };
+
+    if queued_job {
+        update_job(&job_id, |job| {
+            job.state = JobState::Cancelled;
+            job.finished_at = Some(now());
+        });
+    }
+    Ok(())
+}
+
+/// Queue a failed or cancelled job again; returns the new job's ID
+#[tauri::command]
+pub fn retry_job(job_id: String) -> Result<String, String> {
+    let request = {
+        let store = store().0.lock().map_err(|e| e.to_string())?;
+        let job = store.jobs.iter().find(|j| j.id == job_id)
+            .ok_or_else(|| format!("Job not found: {}", job_id))?;
+        if !matches!(job.state, JobState::Failed | JobState::Cancelled) {
+            return Err(format!("Only failed or cancelled jobs can be retried (job {} is {:?})", job_id, job.state));
+        }
+        job.request.clone()
+    };
+
+    enqueue(request, Some(job_id))
+}
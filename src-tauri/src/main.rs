@@ -1,13 +1,40 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod cache_dir;
 mod db;
+mod encoding;
+mod hls;
+mod jobs;
+mod media;
+mod mp4;
+mod originals;
+mod playable;
+mod previews;
+mod probe_cache;
+mod storage;
+mod streaming;
+mod subtitles;
+mod thumbnails;
+mod toolchain;
+mod video_server;
+mod youtube_thumbnails;
 
-use db::{get_user_data, save_user_data, save_video_progress, test_db_connection, check_default_channels, force_initialize_default_channels, set_resource_dir, import_playlist_file, export_playlist, overwrite_playlist_file, export_tab, import_tab_file, save_video_metadata, get_video_metadata_batch, save_video_metadata_batch, scan_local_folder, save_thumbnail, get_thumbnail_path_command, get_thumbnail_data_url, extract_video_thumbnail, get_file_size, convert_mkv_to_mp4, convert_mkv_folder_to_mp4, make_video_web_ready, add_faststart_in_place, convert_hevc_to_h264};
+use db::{get_user_data, save_user_data, repair_library, save_video_progress, test_db_connection, check_default_channels, force_initialize_default_channels, set_resource_dir, import_playlist_file, export_playlist, overwrite_playlist_file, export_tab, import_tab_file, create_tab, rename_tab, reorder_tabs, delete_tab, add_playlist_to_tab, remove_playlist_from_tab, save_video_metadata, get_video_metadata_batch, save_video_metadata_batch, scan_local_folder, save_thumbnail, get_thumbnail_path_command, get_thumbnail_data_url, extract_video_thumbnail, get_file_size, convert_mkv_to_mp4, convert_mkv_folder_to_mp4, make_video_web_ready, add_faststart_in_place, convert_hevc_to_h264};
+use media::list_media_streams;
+use encoding::{get_encoding_profiles, save_encoding_profile, delete_encoding_profile, get_hardware_encoders};
+use playable::ensure_playable;
+use storage::get_storage_usage;
+use thumbnails::{get_thumbnail, get_thumbnail_settings, set_thumbnail_settings, get_thumbnail_cache_stats, evict_thumbnail_cache, sweep_thumbnail_orphans, extract_thumbnails_batch};
+use toolchain::{get_media_toolchain, get_media_toolchain_settings, set_media_toolchain_settings};
+use originals::{get_originals_settings, set_originals_settings, list_original_backups, restore_original};
+use jobs::{enqueue_job, list_jobs, cancel_job, retry_job};
+use hls::{get_hls_cache_settings, set_hls_cache_settings, evict_hls_cache};
+use subtitles::extract_subtitle_track;
+use youtube_thumbnails::warm_thumbnail_cache;
+use video_server::{get_library_roots, set_library_roots, get_video_server_settings, set_video_server_settings};
 use serde::{Serialize, Deserialize};
 use tauri::Manager;
-use tiny_http::{Header, Response, Server, ListenAddr};
-use std::fs::File;
-use std::io::{Read, Seek, SeekFrom};
+use tiny_http::{Server, ListenAddr};
 use std::sync::{Arc, Mutex};
 use std::thread;
 
@@ -27,10 +54,13 @@ pub struct VideoDebugInfo {
     pub ffmpeg_version: Option<String>,
     pub ffprobe_available: bool,
     pub moov_at_start: Option<bool>,
+    pub mp4_brand: Option<String>,
+    pub fragmented: Option<bool>,
     pub file_format: Option<String>,
     pub video_codec: Option<String>,
     pub audio_codec: Option<String>,
     pub duration: Option<f64>,
+    pub streams: Vec<media::MediaStream>,
     pub errors: Vec<String>,
 }
 
@@ -46,10 +76,13 @@ fn get_video_debug_info(app: tauri::AppHandle, file_path: String) -> Result<Vide
         ffmpeg_version: None,
         ffprobe_available: false,
         moov_at_start: None,
+        mp4_brand: None,
+        fragmented: None,
         file_format: None,
         video_codec: None,
         audio_codec: None,
         duration: None,
+        streams: Vec::new(),
         errors: Vec::new(),
     };
 
@@ -83,99 +116,39 @@ fn get_video_debug_info(app: tauri::AppHandle, file_path: String) -> Result<Vide
         info.errors.push(format!("File does not exist: {}", file_path));
     }
 
-    // Check FFmpeg availability
-    match std::process::Command::new("ffmpeg")
-        .arg("-version")
-        .output()
-    {
-        Ok(output) => {
-            info.ffmpeg_available = output.status.success();
-            if info.ffmpeg_available {
-                info.ffmpeg_version = String::from_utf8_lossy(&output.stdout)
-                    .lines()
-                    .next()
-                    .map(|s| s.to_string());
-            }
-        }
-        Err(e) => {
-            info.errors.push(format!("FFmpeg not found: {}", e));
-        }
+    // Check FFmpeg/FFprobe availability (cached toolchain info)
+    let tools = toolchain::toolchain();
+    info.ffmpeg_available = tools.ffmpeg.available;
+    info.ffmpeg_version = tools.ffmpeg.version.clone();
+    info.ffprobe_available = tools.ffprobe.available;
+    if let Some(e) = &tools.ffmpeg.error {
+        info.errors.push(format!("FFmpeg not available at {}: {}", tools.ffmpeg.path, e));
+    }
+    if let Some(e) = &tools.ffprobe.error {
+        info.errors.push(format!("FFprobe not available at {}: {}", tools.ffprobe.path, e));
     }
 
-    // Check FFprobe availability
-    match std::process::Command::new("ffprobe")
-        .arg("-version")
-        .output()
-    {
-        Ok(output) => {
-            info.ffprobe_available = output.status.success();
-        }
-        Err(e) => {
-            info.errors.push(format!("FFprobe not found: {}", e));
+    // MP4 layout straight from the box headers (works without FFprobe)
+    if info.file_readable {
+        if let Ok(layout) = mp4::read_layout(&path) {
+            info.moov_at_start = layout.moov_at_start;
+            info.mp4_brand = layout.major_brand;
+            info.fragmented = Some(layout.fragmented);
         }
     }
 
-    // Use FFprobe to get detailed file info
+    // Use FFprobe (via the probe cache) to get detailed file info
     if info.ffprobe_available && info.file_exists {
-        match std::process::Command::new("ffprobe")
-            .args([
-                "-v", "error",
-                "-print_format", "json",
-                "-show_format",
-                "-show_streams",
-                &file_path,
-            ])
-            .output()
-        {
-            Ok(output) => {
-                if output.status.success() {
-                    if let Ok(json_str) = String::from_utf8(output.stdout) {
-                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&json_str) {
-                            // Extract format info
-                            if let Some(format) = json.get("format") {
-                                if let Some(duration) = format.get("duration")
-                                    .and_then(|d| d.as_str())
-                                    .and_then(|s| s.parse::<f64>().ok())
-                                {
-                                    info.duration = Some(duration);
-                                }
-                                
-                                // Check for faststart tag (indicates moov at start)
-                                if let Some(tags) = format.get("tags") {
-                                    if let Some(faststart) = tags.get("faststart") {
-                                        info.moov_at_start = faststart.as_str().map(|s| s == "1" || s == "yes");
-                                    }
-                                }
-                                
-                                // Get format name
-                                if let Some(format_name) = format.get("format_name") {
-                                    info.file_format = format_name.as_str().map(|s| s.to_string());
-                                }
-                            }
-
-                            // Extract codec info
-                            if let Some(streams) = json.get("streams").and_then(|s| s.as_array()) {
-                                for stream in streams {
-                                    if let Some(codec_type) = stream.get("codec_type").and_then(|t| t.as_str()) {
-                                        if let Some(codec_name) = stream.get("codec_name").and_then(|n| n.as_str()) {
-                                            match codec_type {
-                                                "video" => info.video_codec = Some(codec_name.to_string()),
-                                                "audio" => info.audio_codec = Some(codec_name.to_string()),
-                                                _ => {}
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    info.errors.push(format!("FFprobe failed: {}", stderr));
-                }
+        match probe_cache::media_info(&path) {
+            Ok(media) => {
+                info.file_format = media.container;
+                info.video_codec = media.video_codec;
+                info.audio_codec = media.audio_codec;
+                info.duration = media.duration;
+                info.streams = media.streams;
             }
             Err(e) => {
-                info.errors.push(format!("Failed to run FFprobe: {}", e));
+                info.errors.push(e);
             }
         }
     }
@@ -199,16 +172,6 @@ fn get_port_from_listen_addr(addr: &ListenAddr) -> Result<u16, String> {
     Err("Could not extract port from server address".to_string())
 }
 
-fn parse_range(header: &str, size: u64) -> Option<(u64, u64)> {
-    header.strip_prefix("bytes=")?
-        .split_once('-')
-        .and_then(|(s, e)| {
-            let start = s.parse().ok()?;
-            let end = if e.is_empty() { size - 1 } else { e.parse().ok()? };
-            Some((start, end.min(size - 1)))
-        })
-}
-
 #[tauri::command]
 fn start_video_server(app: tauri::AppHandle) -> Result<u16, String> {
     let state = app.state::<Arc<AppState>>();
@@ -224,74 +187,129 @@ fn start_video_server(app: tauri::AppHandle) -> Result<u16, String> {
     let port = get_port_from_listen_addr(&listen_addr)?;
     *port_lock = port;
 
+    let max_connections = video_server::load_server_settings().max_connections;
     let server_clone = server.clone();
     thread::spawn(move || {
-        for request in server_clone.incoming_requests() {
-            if !request.url().starts_with("/video/") {
-                let _ = request.respond(Response::from_string("404").with_status_code(404));
-                continue;
-            }
+        video_server::run(server_clone, max_connections);
+    });
 
-            let encoded_path = request.url().strip_prefix("/video/").unwrap();
-            let path = match urlencoding::decode(encoded_path) {
-                Ok(p) => p.into_owned(),
-                Err(_) => {
-                    let _ = request.respond(Response::from_string("Bad path").with_status_code(400));
-                    continue;
-                }
-            };
-
-            let mut file = match File::open(&path) {
-                Ok(f) => f,
-                Err(_) => {
-                    let _ = request.respond(Response::from_string("Not found").with_status_code(404));
-                    continue;
-                }
-            };
-            
-            let file_size = match file.metadata() {
-                Ok(m) => m.len(),
-                Err(_) => {
-                    let _ = request.respond(Response::from_string("Cannot read file").with_status_code(500));
-                    continue;
-                }
-            };
-
-            let (start, end, partial) = request.headers()
-                .iter()
-                .find(|h| h.field.equiv("Range"))
-                .and_then(|h| parse_range(h.value.as_str(), file_size))
-                .map(|(s, e)| (s, e, true))
-                .unwrap_or((0, file_size.saturating_sub(1), false));
-
-            let length = end - start + 1;
-            if let Err(_) = file.seek(SeekFrom::Start(start)) {
-                let _ = request.respond(Response::from_string("Seek error").with_status_code(500));
-                continue;
-            }
+    Ok(port)
+}
 
-            // Determine MIME type from file extension (Grok's fix - use as_ref() for proper type)
-            let mime = mime_guess::from_path(&path).first_or_octet_stream();
-
-            let mut resp = Response::empty(if partial { 206 } else { 200 })
-                .with_header(Header::from_bytes("Accept-Ranges", b"bytes").unwrap())
-                .with_header(Header::from_bytes("Content-Length", length.to_string().as_bytes()).unwrap())
-                .with_header(Header::from_bytes("Content-Type", mime.as_ref().as_bytes()).unwrap());
-
-            if partial {
-                resp = resp.with_header(
-                    Header::from_bytes(
-                        "Content-Range",
-                        format!("bytes {start}-{end}/{file_size}").as_bytes()
-                    ).unwrap()
-                );
-            }
+/// Register a local file with the video server and return its URL (/media/<token>).
+/// Only registered files and files under the library roots can be streamed.
+#[tauri::command]
+fn register_media(app: tauri::AppHandle, path: String) -> Result<String, String> {
+    let port = start_video_server(app)?;
+    let token = video_server::register_media(&path)?;
+    Ok(format!("http://127.0.0.1:{}/media/{}", port, token))
+}
 
-            let _ = request.respond(resp.with_data(file.take(length), Some(length as usize)));
-        }
-    });
+#[derive(Serialize, Debug)]
+pub struct StreamInfo {
+    pub url: String,
+    pub plan: streaming::StreamPlan,
+}
 
-    Ok(port)
+/// Get an on-the-fly stream URL (/stream/<token>) for a file the webview can't play directly.
+/// FFmpeg remuxes (or transcodes) to fragmented MP4 while playing, so no converted copy is
+/// written to disk. Append `t=<seconds>` to the URL's query to start (seek) at a given time.
+/// `audio_stream` (an index from `list_media_streams`) picks the audio track.
+#[tauri::command]
+fn get_stream_url(app: tauri::AppHandle, path: String, audio_stream: Option<u32>) -> Result<StreamInfo, String> {
+    let plan = streaming::plan_stream(&path, audio_stream)?;
+    let port = start_video_server(app)?;
+    let token = video_server::register_media(&path)?;
+    let query = audio_stream.map(|i| format!("?audio={}", i)).unwrap_or_default();
+    Ok(StreamInfo {
+        url: format!("http://127.0.0.1:{}/stream/{}{}", port, token, query),
+        plan,
+    })
+}
+
+#[derive(Serialize, Debug)]
+pub struct HlsInfo {
+    pub url: String,
+    pub status: hls::HlsStatus,
+}
+
+/// Package a file as HLS ahead of time (in the background) and return its playlist URL.
+/// Requesting the URL also starts packaging on demand. `retry` clears a previous failure.
+#[tauri::command]
+fn prepare_hls(app: tauri::AppHandle, path: String, retry: Option<bool>) -> Result<HlsInfo, String> {
+    let port = start_video_server(app)?;
+    let token = video_server::register_media(&path)?;
+    let source = video_server::registered_path(&token)
+        .ok_or_else(|| "Failed to register media".to_string())?;
+
+    if retry.unwrap_or(false) {
+        hls::clear_failure(&source)?;
+    }
+    hls::ensure_packaged(&source)?;
+
+    Ok(HlsInfo {
+        url: format!("http://127.0.0.1:{}/hls/{}/index.m3u8", port, token),
+        status: hls::status(&source)?,
+    })
+}
+
+/// List a video's subtitle tracks (embedded streams and sidecar files), with the URL of each
+/// track's WebVTT version for text tracks
+#[tauri::command]
+fn list_subtitle_tracks(app: tauri::AppHandle, path: String) -> Result<Vec<subtitles::SubtitleTrack>, String> {
+    let port = start_video_server(app)?;
+    let token = video_server::register_media(&path)?;
+    let source = video_server::registered_path(&token)
+        .ok_or_else(|| "Failed to register media".to_string())?;
+
+    let mut tracks = subtitles::list_tracks(&source)?;
+    for track in tracks.iter_mut().filter(|t| t.is_text) {
+        track.url = Some(format!("http://127.0.0.1:{}/subtitles/{}/{}.vtt", port, token, track.id));
+    }
+    Ok(tracks)
+}
+
+#[derive(Serialize, Debug)]
+pub struct SeekPreviewInfo {
+    /// WebVTT thumbnails track (cues point at regions of the sprite sheets)
+    pub url: String,
+    pub status: previews::PreviewStatus,
+}
+
+/// Generate seek-bar preview sprite sheets as a background job (a frame every `interval_seconds`,
+/// default 10, `tile_width` px wide, default 160) and return the thumbnails track URL.
+/// The track is served once `status.ready`; `retry` clears a previous failure.
+#[tauri::command]
+fn prepare_seek_previews(app: tauri::AppHandle, path: String, interval_seconds: Option<u32>, tile_width: Option<u32>, retry: Option<bool>) -> Result<SeekPreviewInfo, String> {
+    let port = start_video_server(app)?;
+    let token = video_server::register_media(&path)?;
+    let source = video_server::registered_path(&token)
+        .ok_or_else(|| "Failed to register media".to_string())?;
+
+    let status = previews::ensure_previews(&source, interval_seconds, tile_width, retry.unwrap_or(false))?;
+    Ok(SeekPreviewInfo {
+        url: format!("http://127.0.0.1:{}/previews/{}/{}/{}", port, token, status.variant, previews::TRACK_FILE),
+        status,
+    })
+}
+
+#[derive(Serialize, Debug)]
+pub struct ThumbnailUrlTemplate {
+    /// e.g. "http://127.0.0.1:1234/thumb/{video_id}?size={size}"; the video ID must be
+    /// URL-encoded (encodeURIComponent), size is "small" or "large"
+    pub template: String,
+    pub port: u16,
+}
+
+/// URL template for loading thumbnails from the video server with plain `<img>` tags
+/// (instead of base64 data URLs over IPC). Starts the server if needed.
+#[tauri::command]
+fn get_thumbnail_url_template(app: tauri::AppHandle) -> Result<ThumbnailUrlTemplate, String> {
+    let port = start_video_server(app)?;
+    Ok(ThumbnailUrlTemplate {
+        template: format!("http://127.0.0.1:{}/thumb/{{video_id}}?size={{size}}", port),
+        port,
+    })
 }
 
 // Devtools will be handled via frontend JavaScript
@@ -304,6 +322,7 @@ fn main() {
       test_db_connection,
       get_user_data,
       save_user_data,
+      repair_library,
       save_video_progress,
       check_default_channels,
       force_initialize_default_channels,
@@ -312,6 +331,12 @@ fn main() {
       overwrite_playlist_file,
       export_tab,
       import_tab_file,
+      create_tab,
+      rename_tab,
+      reorder_tabs,
+      delete_tab,
+      add_playlist_to_tab,
+      remove_playlist_from_tab,
       save_video_metadata,
       get_video_metadata_batch,
       save_video_metadata_batch,
@@ -326,13 +351,66 @@ fn main() {
       make_video_web_ready,
       add_faststart_in_place,
       convert_hevc_to_h264,
+      enqueue_job,
+      list_jobs,
+      cancel_job,
+      retry_job,
+      get_encoding_profiles,
+      save_encoding_profile,
+      delete_encoding_profile,
+      get_hardware_encoders,
       start_video_server,
-      get_video_debug_info
+      register_media,
+      get_stream_url,
+      prepare_hls,
+      get_hls_cache_settings,
+      set_hls_cache_settings,
+      evict_hls_cache,
+      list_subtitle_tracks,
+      prepare_seek_previews,
+      get_thumbnail_url_template,
+      extract_subtitle_track,
+      get_library_roots,
+      set_library_roots,
+      get_video_server_settings,
+      set_video_server_settings,
+      get_video_debug_info,
+      list_media_streams,
+      ensure_playable,
+      get_originals_settings,
+      set_originals_settings,
+      list_original_backups,
+      restore_original,
+      get_storage_usage,
+      get_media_toolchain,
+      get_media_toolchain_settings,
+      set_media_toolchain_settings,
+      get_thumbnail,
+      get_thumbnail_settings,
+      set_thumbnail_settings,
+      get_thumbnail_cache_stats,
+      evict_thumbnail_cache,
+      sweep_thumbnail_orphans,
+      extract_thumbnails_batch,
+      warm_thumbnail_cache
     ])
     .plugin(tauri_plugin_dialog::init())
     .plugin(tauri_plugin_fs::init())
     .plugin(tauri_plugin_shell::init())
-    .setup(|_app| {
+    .setup(|app| {
+      jobs::init(app.handle().clone());
+      
+      // Delete originals kept longer than the retention period, and orphaned thumbnails
+      thread::spawn(|| {
+        if let Err(e) = originals::prune_expired() {
+          eprintln!("⚠️ Could not prune old originals: {}", e);
+        }
+        // Thumbnails of videos that are no longer in any playlist
+        if let Err(e) = thumbnails::sweep_orphans() {
+          eprintln!("⚠️ Could not sweep thumbnails: {}", e);
+        }
+      });
+      
       // Set resource directory for accessing bundled files like default-channels.json
       // Tauri NSIS installers place resources in _up_ subdirectory
       if let Ok(exe_path) = std::env::current_exe() {
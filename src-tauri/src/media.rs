@@ -0,0 +1,204 @@
+// Shared FFprobe helpers for local media files
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Run ffprobe and return its JSON (format + streams)
+pub fn ffprobe_json(path: &str) -> Result<serde_json::Value, String> {
+    let output = crate::toolchain::ffprobe()
+        .args([
+            "-v", "error",
+            "-print_format", "json",
+            "-show_format",
+            "-show_streams",
+            path,
+        ])
+        .output()
+        .map_err(|e| crate::toolchain::spawn_error("FFprobe", &e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("FFprobe failed: {}", stderr.trim()));
+    }
+
+    serde_json::from_slice(&output.stdout).map_err(|e| format!("Invalid FFprobe output: {}", e))
+}
+
+/// Codec of the first stream of a type ("video", "audio", "subtitle")
+pub fn first_codec(probe: &serde_json::Value, codec_type: &str) -> Option<String> {
+    probe.get("streams")?
+        .as_array()?
+        .iter()
+        .find(|s| s.get("codec_type").and_then(|t| t.as_str()) == Some(codec_type))
+        .and_then(|s| s.get("codec_name"))
+        .and_then(|n| n.as_str())
+        .map(|s| s.to_string())
+}
+
+/// Container duration in seconds
+pub fn duration(probe: &serde_json::Value) -> Option<f64> {
+    probe.get("format")?
+        .get("duration")?
+        .as_str()?
+        .parse()
+        .ok()
+}
+
+/// Video codecs every webview can decode inside MP4
+pub fn is_browser_video_codec(codec: &str) -> bool {
+    matches!(codec, "h264")
+}
+
+/// Audio codecs every webview can decode inside MP4
+pub fn is_browser_audio_codec(codec: &str) -> bool {
+    matches!(codec, "aac" | "mp3")
+}
+
+/// FNV-1a hash - stable across Rust versions and platforms (unlike DefaultHasher),
+/// so it's safe for naming files that must survive app updates
+pub fn stable_hash(value: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in value.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// File size and mtime (seconds since the epoch) - together with the path they identify a
+/// version of a file for caches
+pub fn file_stamp(path: &Path) -> Result<(u64, i64), String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Cannot read file metadata: {}", e))?;
+    let mtime = metadata.modified().ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    Ok((metadata.len(), mtime))
+}
+
+/// Cache key for derived files (HLS packages, subtitles, ...) from path + size + mtime,
+/// so editing or replacing the source invalidates them
+pub fn file_cache_key(path: &Path) -> Result<String, String> {
+    let (size, mtime) = file_stamp(path)?;
+    Ok(format!("{:016x}", stable_hash(&format!("{}|{}|{}", path.display(), size, mtime))))
+}
+
+/// One stream of a media file (from FFprobe)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MediaStream {
+    /// Absolute stream index (use with `-map 0:<index>`)
+    pub index: u32,
+    /// "video", "audio", "subtitle", "attachment", ...
+    pub codec_type: String,
+    pub codec_name: Option<String>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    pub is_default: bool,
+    pub channels: Option<u32>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub bit_rate: Option<u64>,
+}
+
+/// Parse the streams from FFprobe JSON
+pub fn parse_streams(probe: &serde_json::Value) -> Vec<MediaStream> {
+    let streams = match probe.get("streams").and_then(|s| s.as_array()) {
+        Some(streams) => streams,
+        None => return Vec::new(),
+    };
+
+    streams.iter()
+        .filter_map(|stream| {
+            let str_field = |name: &str| stream.get(name).and_then(|v| v.as_str()).map(|s| s.to_string());
+            let u64_field = |name: &str| stream.get(name).and_then(|v| v.as_u64());
+            let tag = |name: &str| stream.get("tags").and_then(|t| t.get(name)).and_then(|v| v.as_str()).map(|s| s.to_string());
+
+            Some(MediaStream {
+                index: u64_field("index")? as u32,
+                codec_type: str_field("codec_type").unwrap_or_default(),
+                codec_name: str_field("codec_name"),
+                language: tag("language"),
+                title: tag("title"),
+                is_default: stream.get("disposition").and_then(|d| d.get("default")).and_then(|v| v.as_i64()) == Some(1),
+                channels: u64_field("channels").map(|c| c as u32),
+                width: u64_field("width").map(|w| w as u32),
+                height: u64_field("height").map(|h| h as u32),
+                // FFprobe reports bit_rate as a string
+                bit_rate: str_field("bit_rate").and_then(|b| b.parse().ok()),
+            })
+        })
+        .collect()
+}
+
+/// List the streams (video, audio, subtitles, ...) in a media file
+#[tauri::command]
+pub fn list_media_streams(path: String) -> Result<Vec<MediaStream>, String> {
+    Ok(parse_streams(&ffprobe_json(&path)?))
+}
+
+/// Which streams to keep when converting or streaming. Empty selection = FFmpeg's defaults
+/// (one video + one audio stream).
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StreamSelection {
+    /// Audio streams to keep, by absolute stream index (from `list_media_streams`)
+    #[serde(default)]
+    pub audio_streams: Vec<u32>,
+    /// Keep the first audio stream in this language (ISO 639 code, e.g. "jpn") if no indices given
+    #[serde(default)]
+    pub audio_language: Option<String>,
+    /// Keep every audio stream
+    #[serde(default)]
+    pub all_audio: bool,
+}
+
+impl StreamSelection {
+    pub fn is_default(&self) -> bool {
+        self.audio_streams.is_empty() && self.audio_language.is_none() && !self.all_audio
+    }
+}
+
+/// Resolve a selection to the audio stream indices to map (None = let FFmpeg choose)
+pub fn resolve_audio_streams(streams: &[MediaStream], selection: &StreamSelection) -> Result<Option<Vec<u32>>, String> {
+    if selection.is_default() {
+        return Ok(None);
+    }
+
+    let audio: Vec<&MediaStream> = streams.iter().filter(|s| s.codec_type == "audio").collect();
+
+    if !selection.audio_streams.is_empty() {
+        if let Some(missing) = selection.audio_streams.iter().find(|i| !audio.iter().any(|s| s.index == **i)) {
+            return Err(format!("Audio stream {} not found", missing));
+        }
+        return Ok(Some(selection.audio_streams.clone()));
+    }
+
+    if let Some(language) = &selection.audio_language {
+        return audio.iter()
+            .find(|s| s.language.as_deref().map_or(false, |l| l.eq_ignore_ascii_case(language)))
+            .map(|s| Some(vec![s.index]))
+            .ok_or_else(|| format!("No audio stream in language '{}'", language));
+    }
+
+    Ok(Some(audio.iter().map(|s| s.index).collect()))
+}
+
+/// FFmpeg `-map` arguments for a selection: the first video stream plus the chosen audio streams.
+/// Returns an empty list for the default selection (FFmpeg picks streams itself). The streams
+/// come from the probe cache, so callers that just probed the file don't run ffprobe again.
+pub fn stream_map_args(input_path: &str, selection: Option<&StreamSelection>) -> Result<Vec<String>, String> {
+    let selection = match selection {
+        Some(s) if !s.is_default() => s,
+        _ => return Ok(Vec::new()),
+    };
+
+    let info = crate::probe_cache::media_info(Path::new(input_path))?;
+    let audio = resolve_audio_streams(&info.streams, selection)?.unwrap_or_default();
+
+    let mut args = vec!["-map".to_string(), "0:v:0?".to_string()];
+    for index in audio {
+        args.push("-map".to_string());
+        args.push(format!("0:{}", index));
+    }
+    Ok(args)
+}
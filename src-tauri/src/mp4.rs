@@ -0,0 +1,312 @@
+// Minimal MP4 / ISO-BMFF box walker. Reads only box headers (and ftyp), so it answers
+// "where is the moov?" and "is this fragmented?" instantly even for multi-GB files,
+// without FFprobe.
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Stop walking after this many top-level boxes (fragmented files have one moof per fragment)
+const MAX_BOXES: usize = 10_000;
+
+/// A top-level box
+#[derive(Serialize, Debug, Clone)]
+pub struct Mp4Box {
+    /// Four-character type, e.g. "ftyp", "moov", "mdat"
+    pub box_type: String,
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Layout of an MP4 file's top-level boxes
+#[derive(Serialize, Debug, Clone)]
+pub struct Mp4Layout {
+    /// ftyp major brand, e.g. "isom", "mp42", "qt  "
+    pub major_brand: Option<String>,
+    pub compatible_brands: Vec<String>,
+    pub moov_offset: Option<u64>,
+    pub mdat_offset: Option<u64>,
+    /// moov before the first mdat (playback can start before the whole file is read).
+    /// None if either box is missing (e.g. an incomplete download has no moov yet).
+    pub moov_at_start: Option<bool>,
+    /// Fragmented MP4 (moov has an mvex box / file has moof boxes); needs no faststart
+    pub fragmented: bool,
+    /// Top-level boxes up to the point the walk stopped
+    pub boxes: Vec<Mp4Box>,
+}
+
+impl Mp4Layout {
+    /// Whether `-movflags +faststart` would change anything
+    pub fn needs_faststart(&self) -> bool {
+        !self.fragmented && self.moov_at_start != Some(true)
+    }
+}
+
+/// Read a box header at `offset`: (type, total size, header size). Size 0 means "to end of file".
+fn read_box_header<R: Read + Seek>(file: &mut R, offset: u64, end: u64) -> Option<([u8; 4], u64, u64)> {
+    if offset.checked_add(8)? > end {
+        return None;
+    }
+
+    let mut header = [0u8; 8];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut header).ok()?;
+    let box_type = [header[4], header[5], header[6], header[7]];
+
+    let (size, header_len) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+        0 => (end - offset, 8),
+        1 => {
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large).ok()?;
+            (u64::from_be_bytes(large), 16)
+        }
+        n => (n as u64, 8),
+    };
+
+    if size < header_len {
+        return None;
+    }
+    Some((box_type, size, header_len))
+}
+
+fn fourcc(box_type: &[u8]) -> String {
+    box_type.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '?' }).collect()
+}
+
+/// Whether a container box (moov) has a direct child of the given type
+fn has_child<R: Read + Seek>(file: &mut R, start: u64, end: u64, child: &[u8; 4]) -> bool {
+    let mut offset = start;
+    while let Some((box_type, size, _)) = read_box_header(file, offset, end) {
+        if &box_type == child {
+            return true;
+        }
+        offset = match offset.checked_add(size) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    false
+}
+
+/// Walk the top-level boxes of an MP4/MOV file
+pub fn read_layout(path: &Path) -> Result<Mp4Layout, String> {
+    let mut file = File::open(path).map_err(|e| format!("Cannot open file: {}", e))?;
+    let file_len = file.metadata().map_err(|e| format!("Cannot read file metadata: {}", e))?.len();
+    walk_layout(&mut file, file_len)
+}
+
+/// Walk the top-level boxes of `file_len` bytes of MP4/MOV data
+fn walk_layout<R: Read + Seek>(file: &mut R, file_len: u64) -> Result<Mp4Layout, String> {
+    let mut layout = Mp4Layout {
+        major_brand: None,
+        compatible_brands: Vec::new(),
+        moov_offset: None,
+        mdat_offset: None,
+        moov_at_start: None,
+        fragmented: false,
+        boxes: Vec::new(),
+    };
+
+    let mut offset = 0u64;
+    while layout.boxes.len() < MAX_BOXES {
+        let (box_type, size, header_len) = match read_box_header(file, offset, file_len) {
+            Some(header) => header,
+            None => break,
+        };
+
+        // Every MP4/MOV starts with one of these
+        if offset == 0 && !matches!(&box_type, b"ftyp" | b"free" | b"skip" | b"wide" | b"pnot" | b"moov" | b"mdat") {
+            return Err(format!("Not an MP4 file (first box is '{}')", fourcc(&box_type)));
+        }
+
+        layout.boxes.push(Mp4Box { box_type: fourcc(&box_type), offset, size });
+        // The header was read, so this is within the file
+        let body = offset + header_len;
+
+        match &box_type {
+            b"ftyp" => {
+                let mut brands = vec![0u8; (size - header_len).min(file_len.saturating_sub(body)).min(256) as usize];
+                file.seek(SeekFrom::Start(body)).map_err(|e| e.to_string())?;
+                file.read_exact(&mut brands).map_err(|e| format!("Truncated ftyp box: {}", e))?;
+                if brands.len() >= 4 {
+                    layout.major_brand = Some(fourcc(&brands[..4]));
+                }
+                // major brand (4) + minor version (4), then compatible brands
+                layout.compatible_brands = brands.get(8..).unwrap_or_default()
+                    .chunks_exact(4)
+                    .map(fourcc)
+                    .collect();
+            }
+            b"moov" if layout.moov_offset.is_none() => {
+                layout.moov_offset = Some(offset);
+                if has_child(file, body, offset.saturating_add(size).min(file_len), b"mvex") {
+                    layout.fragmented = true;
+                }
+            }
+            b"mdat" if layout.mdat_offset.is_none() => layout.mdat_offset = Some(offset),
+            b"moof" => layout.fragmented = true,
+            _ => {}
+        }
+
+        // Everything interesting has been seen; don't walk every fragment of a long file
+        if layout.moov_offset.is_some() && (layout.mdat_offset.is_some() || layout.fragmented) {
+            break;
+        }
+        offset = match offset.checked_add(size) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    if let (Some(moov), Some(mdat)) = (layout.moov_offset, layout.mdat_offset) {
+        layout.moov_at_start = Some(moov < mdat);
+    } else if layout.fragmented && layout.moov_offset.is_some() {
+        layout.moov_at_start = Some(true);
+    }
+
+    if layout.boxes.is_empty() {
+        return Err("Not an MP4 file (no boxes found)".to_string());
+    }
+
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn mp4_box(box_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(box_type);
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn ftyp() -> Vec<u8> {
+        mp4_box(b"ftyp", b"isom\0\0\x02\0isomiso2mp41")
+    }
+
+    fn walk(data: &[u8]) -> Result<Mp4Layout, String> {
+        walk_layout(&mut Cursor::new(data), data.len() as u64)
+    }
+
+    fn box_types(layout: &Mp4Layout) -> Vec<&str> {
+        layout.boxes.iter().map(|b| b.box_type.as_str()).collect()
+    }
+
+    #[test]
+    fn moov_before_mdat_is_faststart() {
+        let data = [ftyp(), mp4_box(b"moov", &mp4_box(b"mvhd", &[0; 12])), mp4_box(b"mdat", &[1; 32])].concat();
+        let layout = walk(&data).unwrap();
+        assert_eq!(layout.major_brand.as_deref(), Some("isom"));
+        assert_eq!(layout.compatible_brands, vec!["isom", "iso2", "mp41"]);
+        assert_eq!(box_types(&layout), vec!["ftyp", "moov", "mdat"]);
+        assert_eq!(layout.moov_at_start, Some(true));
+        assert!(!layout.fragmented);
+        assert!(!layout.needs_faststart());
+    }
+
+    #[test]
+    fn moov_after_mdat_needs_faststart() {
+        let data = [ftyp(), mp4_box(b"mdat", &[1; 32]), mp4_box(b"moov", &mp4_box(b"mvhd", &[0; 12]))].concat();
+        let layout = walk(&data).unwrap();
+        assert_eq!(layout.mdat_offset, Some(ftyp().len() as u64));
+        assert_eq!(layout.moov_offset, Some(ftyp().len() as u64 + 40));
+        assert_eq!(layout.moov_at_start, Some(false));
+        assert!(layout.needs_faststart());
+    }
+
+    #[test]
+    fn fragmented_moov_needs_no_faststart() {
+        let moov = mp4_box(b"moov", &[mp4_box(b"mvhd", &[0; 12]), mp4_box(b"mvex", &[])].concat());
+        let data = [ftyp(), moov, mp4_box(b"moof", &[0; 8])].concat();
+        let layout = walk(&data).unwrap();
+        assert!(layout.fragmented);
+        assert_eq!(layout.moov_at_start, Some(true));
+        assert!(!layout.needs_faststart());
+    }
+
+    #[test]
+    fn truncated_box_stops_the_walk() {
+        // mdat claims 1000 bytes but the download stopped after 16: no moov yet
+        let mut data = ftyp();
+        data.extend_from_slice(&1000u32.to_be_bytes());
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&[1; 8]);
+        let layout = walk(&data).unwrap();
+        assert_eq!(box_types(&layout), vec!["ftyp", "mdat"]);
+        assert_eq!(layout.moov_offset, None);
+        assert_eq!(layout.moov_at_start, None);
+        assert!(layout.needs_faststart());
+
+        // A header cut off after the size field isn't a box
+        let layout = walk(&[ftyp(), vec![0, 0, 0, 16]].concat()).unwrap();
+        assert_eq!(box_types(&layout), vec!["ftyp"]);
+    }
+
+    #[test]
+    fn size_smaller_than_header_stops_the_walk() {
+        let mut data = ftyp();
+        data.extend_from_slice(&4u32.to_be_bytes());
+        data.extend_from_slice(b"free");
+        data.extend(mp4_box(b"moov", &[]));
+        let layout = walk(&data).unwrap();
+        assert_eq!(box_types(&layout), vec!["ftyp"]);
+    }
+
+    #[test]
+    fn size_zero_runs_to_end_of_file() {
+        let moov = mp4_box(b"moov", &mp4_box(b"mvhd", &[0; 12]));
+        let mut data = [ftyp(), moov].concat();
+        let mdat_offset = data.len() as u64;
+        data.extend_from_slice(&0u32.to_be_bytes());
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&[1; 100]);
+        let layout = walk(&data).unwrap();
+        let mdat = layout.boxes.last().unwrap();
+        assert_eq!(mdat.box_type, "mdat");
+        assert_eq!(mdat.offset, mdat_offset);
+        assert_eq!(mdat.size, 108);
+        assert_eq!(layout.moov_at_start, Some(true));
+    }
+
+    #[test]
+    fn largesize_boxes_are_skipped_by_their_64_bit_size() {
+        let mut data = ftyp();
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&48u64.to_be_bytes());
+        data.extend_from_slice(&[1; 32]);
+        data.extend(mp4_box(b"moov", &[]));
+        let layout = walk(&data).unwrap();
+        assert_eq!(box_types(&layout), vec!["ftyp", "mdat", "moov"]);
+        assert_eq!(layout.boxes[1].size, 48);
+        assert_eq!(layout.moov_at_start, Some(false));
+    }
+
+    #[test]
+    fn largesize_past_u64_max_does_not_overflow() {
+        let mut data = ftyp();
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(b"free");
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+        let layout = walk(&data).unwrap();
+        assert_eq!(box_types(&layout), vec!["ftyp", "free"]);
+
+        let mut moov = 1u32.to_be_bytes().to_vec();
+        moov.extend_from_slice(b"moov");
+        moov.extend_from_slice(&u64::MAX.to_be_bytes());
+        moov.extend(mp4_box(b"mvhd", &[]));
+        let layout = walk(&[ftyp(), moov].concat()).unwrap();
+        assert_eq!(layout.moov_offset, Some(ftyp().len() as u64));
+        assert!(!layout.fragmented);
+    }
+
+    #[test]
+    fn rejects_non_mp4_data() {
+        assert!(walk(&mp4_box(b"RIFF", &[0; 8])).is_err());
+        assert!(walk(&[]).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+// Safe in-place replacement for conversions that overwrite their input (faststart, HEVC to
+// H.264). The converted temp file is probed first and must match the original's duration
+// and stream counts; only then is it moved over the original. The original is kept in
+// <data dir>/originals/ for the configured number of days so `restore_original` can undo a
+// bad conversion. Backups are recorded in the `original_backups` table.
+
+use crate::media;
+use crate::probe_cache::MediaInfo;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SETTINGS_KEY: &str = "original_backups";
+/// Allowed duration difference: whichever is larger (encoders pad/trim a few frames)
+const DURATION_TOLERANCE_SECS: f64 = 1.0;
+const DURATION_TOLERANCE_RATIO: f64 = 0.01;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OriginalsSettings {
+    /// Days to keep replaced originals (0 = don't keep them)
+    pub keep_days: u32,
+}
+
+impl Default for OriginalsSettings {
+    fn default() -> Self {
+        OriginalsSettings { keep_days: 7 }
+    }
+}
+
+/// A replaced original kept in the backup folder
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OriginalBackup {
+    /// Where the file lived (and where `restore_original` puts it back)
+    pub original_path: String,
+    pub backup_path: String,
+    pub size: u64,
+    /// Unix seconds
+    pub created_at: u64,
+    /// What replaced it, e.g. "faststart" or "hevc_to_h264"
+    pub reason: String,
+}
+
+/// What a converted file has to match before it may replace the original
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    pub duration: Option<f64>,
+    pub video_streams: usize,
+    pub audio_streams: usize,
+}
+
+impl Expectation {
+    /// FFmpeg keeps one video stream and `audio_streams` audio streams (default: one, as
+    /// FFmpeg picks a single audio stream without `-map`)
+    pub fn from_original(info: &MediaInfo, audio_streams: Option<usize>) -> Self {
+        let count = |kind: &str| info.streams.iter().filter(|s| s.codec_type == kind).count();
+        Expectation {
+            duration: info.duration,
+            video_streams: count("video").min(1),
+            audio_streams: audio_streams.unwrap_or_else(|| count("audio").min(1)),
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn load_settings() -> OriginalsSettings {
+    crate::db::get_setting(SETTINGS_KEY).ok().flatten().unwrap_or_default()
+}
+
+fn backup_dir() -> Result<PathBuf, String> {
+    crate::db::get_cache_dir("originals")
+}
+
+/// Probe the converted file and compare it with the expectation (None = the original couldn't
+/// be probed, so the output only has to be readable and contain a stream)
+pub fn verify(output: &Path, expected: Option<&Expectation>) -> Result<(), String> {
+    let json = media::ffprobe_json(&output.to_string_lossy())
+        .map_err(|e| format!("Converted file can't be read: {}", e))?;
+    let streams = media::parse_streams(&json);
+    let count = |kind: &str| streams.iter().filter(|s| s.codec_type == kind).count();
+
+    let expected = match expected {
+        Some(expected) => expected,
+        None if streams.is_empty() => return Err("Converted file has no streams".to_string()),
+        None => return Ok(()),
+    };
+
+    if count("video") != expected.video_streams || count("audio") != expected.audio_streams {
+        return Err(format!(
+            "Converted file has {} video / {} audio streams, expected {} / {}",
+            count("video"), count("audio"), expected.video_streams, expected.audio_streams
+        ));
+    }
+
+    if let Some(expected_duration) = expected.duration {
+        let duration = media::duration(&json).ok_or("Converted file has no duration")?;
+        let tolerance = DURATION_TOLERANCE_SECS.max(expected_duration * DURATION_TOLERANCE_RATIO);
+        if (duration - expected_duration).abs() > tolerance {
+            return Err(format!("Converted file is {:.1}s long, expected {:.1}s", duration, expected_duration));
+        }
+    }
+
+    Ok(())
+}
+
+/// Rename, or copy + delete when the backup folder is on another drive
+fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)?;
+    std::fs::remove_file(from).inspect_err(|_| {
+        let _ = std::fs::remove_file(to);
+    })
+}
+
+/// Check, before an in-place conversion starts, that the drive `original` would be backed up
+/// to has room for it (nothing to check when retention is off)
+pub fn preflight(original: &Path) -> Result<(), String> {
+    if load_settings().keep_days == 0 {
+        return Ok(());
+    }
+    let backup_dir = backup_dir()?;
+    crate::storage::check_backup_space(original, &backup_dir)?;
+    Ok(())
+}
+
+/// Verify `converted` and move it over `original`, keeping the original as a backup when
+/// retention is on. On failure the converted file is deleted and the original is untouched.
+pub fn replace_original(original: &Path, converted: &Path, expected: Option<&Expectation>, reason: &str) -> Result<(), String> {
+    if let Err(e) = verify(converted, expected) {
+        let _ = std::fs::remove_file(converted);
+        eprintln!("❌ Verification failed for {}: {}", original.display(), e);
+        return Err(format!("{}. The original file was left unchanged.", e));
+    }
+
+    let keep_days = load_settings().keep_days;
+    if keep_days == 0 {
+        return std::fs::rename(converted, original).map_err(|e| {
+            let _ = std::fs::remove_file(converted);
+            format!("Failed to replace original file: {}", e)
+        });
+    }
+
+    let created_at = now_secs();
+    let file_name = original.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let original_str = original.to_string_lossy().to_string();
+    let backup_path = backup_dir()?.join(format!("{}_{:016x}_{}", created_at, media::stable_hash(&original_str), file_name));
+    let size = std::fs::metadata(original).map(|m| m.len()).unwrap_or(0);
+
+    move_file(original, &backup_path).map_err(|e| {
+        let _ = std::fs::remove_file(converted);
+        format!("Failed to back up original file: {}", e)
+    })?;
+
+    if let Err(e) = std::fs::rename(converted, original) {
+        // Put the original back where it was
+        let _ = move_file(&backup_path, original);
+        let _ = std::fs::remove_file(converted);
+        return Err(format!("Failed to replace original file: {}", e));
+    }
+
+    let backup = OriginalBackup {
+        original_path: original_str,
+        backup_path: backup_path.to_string_lossy().to_string(),
+        size,
+        created_at,
+        reason: reason.to_string(),
+    };
+    if let Err(e) = crate::db::save_original_backup(&backup) {
+        eprintln!("⚠️ Could not record backup of {}: {}", original.display(), e);
+    }
+    eprintln!("🗄️ Kept original for {} days: {}", keep_days, backup.backup_path);
+
+    if let Err(e) = prune_expired() {
+        eprintln!("⚠️ Could not prune old originals: {}", e);
+    }
+    Ok(())
+}
+
+/// Delete backups older than the retention period (and records whose file is gone).
+/// Returns the number of bytes freed.
+pub fn prune_expired() -> Result<u64, String> {
+    let cutoff = now_secs().saturating_sub(load_settings().keep_days as u64 * 24 * 60 * 60);
+    let mut freed = 0;
+
+    for backup in crate::db::load_original_backups(None)? {
+        let exists = Path::new(&backup.backup_path).exists();
+        if exists && backup.created_at >= cutoff {
+            continue;
+        }
+        if exists {
+            if let Err(e) = std::fs::remove_file(&backup.backup_path) {
+                eprintln!("⚠️ Could not delete old original {}: {}", backup.backup_path, e);
+                continue;
+            }
+            eprintln!("🧹 Deleted original kept since {}: {}", backup.created_at, backup.backup_path);
+            freed += backup.size;
+        }
+        crate::db::delete_original_backup(&backup.backup_path)?;
+    }
+
+    Ok(freed)
+}
+
+#[tauri::command]
+pub fn get_originals_settings() -> Result<OriginalsSettings, String> {
+    Ok(load_settings())
+}
+
+/// Save the retention period and delete backups older than it right away
+#[tauri::command]
+pub fn set_originals_settings(settings: OriginalsSettings) -> Result<u64, String> {
+    crate::db::set_setting(SETTINGS_KEY, &settings)?;
+    prune_expired()
+}
+
+/// Kept originals, newest first (optionally only those of one file)
+#[tauri::command]
+pub fn list_original_backups(path: Option<String>) -> Result<Vec<OriginalBackup>, String> {
+    Ok(crate::db::load_original_backups(path.as_deref())?
+        .into_iter()
+        .filter(|b| Path::new(&b.backup_path).exists())
+        .collect())
+}
+
+/// Put the most recent kept original of `path` back, replacing the converted file
+#[tauri::command]
+pub fn restore_original(path: String) -> Result<String, String> {
+    let backup = list_original_backups(Some(path.clone()))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("No kept original for {}", path))?;
+
+    eprintln!("↩️ Restoring original of {} from {}", path, backup.backup_path);
+
+    let target = Path::new(&path);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    // Rename over the converted file, or copy when the backup folder is on another drive
+    if std::fs::rename(&backup.backup_path, target).is_err() {
+        std::fs::copy(&backup.backup_path, target).map_err(|e| format!("Failed to restore original: {}", e))?;
+        let _ = std::fs::remove_file(&backup.backup_path);
+    }
+
+    crate::db::delete_original_backup(&backup.backup_path)?;
+    eprintln!("✅ Restored original: {}", path);
+    Ok(path)
+}
@@ -0,0 +1,273 @@
+// "Make playable": probe a local file and pick the smallest conversion that makes it play in
+// the webview - nothing, a faststart remux (copy streams into an MP4 with the moov box in
+// front), an audio-only transcode, or a full video transcode. `ensure_playable` returns the
+// plan first; with `execute` it also queues the conversion as a background job.
+// `add_faststart_in_place` and `convert_hevc_to_h264` are thin wrappers around `run`.
+
+use crate::encoding::{self, EncodingProfile};
+use crate::media::{self, StreamSelection};
+use crate::originals::{self, Expectation};
+use crate::probe_cache::{self, MediaInfo};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PlayablePolicy {
+    /// Encoding profile for transcodes (default: "default")
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub stream_selection: Option<StreamSelection>,
+    /// Where to write the result (default: MP4s are replaced in place, other containers get
+    /// `<name>.mp4` next to them)
+    #[serde(default)]
+    pub output_path: Option<String>,
+    /// Re-encode even browser-compatible streams (e.g. to apply a profile's size caps)
+    #[serde(default)]
+    pub force_transcode: bool,
+    /// Never transcode: only remux (fixes the container and faststart; codecs the browser
+    /// can't decode are kept as they are)
+    #[serde(default)]
+    pub copy_only: bool,
+    /// Queue the conversion as a job; otherwise only the plan is returned
+    #[serde(default)]
+    pub execute: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayableAction {
+    /// Already plays as-is
+    Nothing,
+    /// Copy all streams into an MP4 with +faststart
+    Faststart,
+    /// Copy the video, transcode the audio
+    AudioTranscode,
+    /// Transcode the video (and the audio if needed)
+    FullTranscode,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PlayablePlan {
+    pub path: String,
+    pub action: PlayableAction,
+    /// Why the action is needed, e.g. "video codec hevc is not browser-compatible"
+    pub reasons: Vec<String>,
+    pub copy_video: bool,
+    pub copy_audio: bool,
+    /// None when nothing needs to be done
+    pub output_path: Option<String>,
+    pub profile: String,
+    pub container: Option<String>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub moov_at_start: Option<bool>,
+    /// Set when the plan was queued (`execute`)
+    pub job_id: Option<String>,
+}
+
+fn is_mp4_container(info: &MediaInfo) -> bool {
+    info.container.as_deref().map_or(false, |c| c.split(',').any(|name| name == "mp4" || name == "mov"))
+}
+
+/// Which streams can be copied as-is (browser-compatible codecs, unless the profile or
+/// policy asks for a re-encode)
+pub fn copyable_streams(info: &MediaInfo, profile: &EncodingProfile, force_transcode: bool) -> (bool, bool) {
+    let copy_video = profile.copies_video()
+        || (!force_transcode && info.video_codec.as_deref().map_or(true, media::is_browser_video_codec));
+    let copy_audio = profile.copies_audio()
+        || (!force_transcode && info.audio_codec.as_deref().map_or(true, media::is_browser_audio_codec));
+    (copy_video, copy_audio)
+}
+
+/// `-c:v`/`-c:a` arguments: copy where possible, the profile's encoders otherwise
+pub fn codec_args(copy_video: bool, copy_audio: bool, profile: &EncodingProfile) -> Vec<String> {
+    let mut args = if copy_video {
+        vec!["-c:v".to_string(), "copy".to_string()]
+    } else {
+        profile.video_args()
+    };
+    if copy_audio {
+        args.extend(["-c:a".to_string(), "copy".to_string()]);
+    } else {
+        args.extend(profile.audio_args());
+    }
+    args
+}
+
+/// Decide what a file needs (probes via the cache; doesn't convert anything)
+pub fn plan(path: &Path, policy: &PlayablePolicy) -> Result<PlayablePlan, String> {
+    if !path.is_file() {
+        return Err(format!("File does not exist: {}", path.display()));
+    }
+
+    let info = probe_cache::media_info(path)?;
+    let profile = encoding::resolve_profile(policy.profile.as_deref())?;
+    let (copy_video, copy_audio) = if policy.copy_only {
+        (true, true)
+    } else {
+        copyable_streams(&info, &profile, policy.force_transcode)
+    };
+    let is_mp4 = is_mp4_container(&info);
+    let mut reasons = Vec::new();
+
+    if !copy_video {
+        reasons.push(match &info.video_codec {
+            Some(codec) if media::is_browser_video_codec(codec) => format!("re-encoding {} video with profile '{}'", codec, profile.name),
+            Some(codec) => format!("video codec {} is not browser-compatible", codec),
+            None => "video needs re-encoding".to_string(),
+        });
+    }
+    if profile.copies_video() {
+        if let Some(codec) = info.video_codec.as_deref().filter(|c| !media::is_browser_video_codec(c)) {
+            reasons.push(format!("profile '{}' keeps the {} video, which may still not play", profile.name, codec));
+        }
+    }
+    if !copy_audio {
+        reasons.push(match &info.audio_codec {
+            Some(codec) if media::is_browser_audio_codec(codec) => format!("re-encoding {} audio with profile '{}'", codec, profile.name),
+            Some(codec) => format!("audio codec {} is not browser-compatible", codec),
+            None => "audio needs re-encoding".to_string(),
+        });
+    }
+    if !is_mp4 {
+        reasons.push(format!("container {} needs remuxing to MP4", info.container.as_deref().unwrap_or("unknown")));
+    } else if info.moov_at_start != Some(true) {
+        reasons.push(match info.moov_at_start {
+            Some(false) => "moov box is at the end of the file (no faststart)".to_string(),
+            _ => "moov box not found (file may be incomplete or damaged)".to_string(),
+        });
+    }
+
+    let selection_changes_streams = policy.stream_selection.as_ref().map_or(false, |s| !s.is_default());
+    if selection_changes_streams && reasons.is_empty() {
+        reasons.push("stream selection drops streams".to_string());
+    }
+
+    let action = if !copy_video {
+        PlayableAction::FullTranscode
+    } else if !copy_audio {
+        PlayableAction::AudioTranscode
+    } else if !reasons.is_empty() {
+        PlayableAction::Faststart
+    } else {
+        PlayableAction::Nothing
+    };
+
+    let output_path = match (&policy.output_path, action) {
+        (_, PlayableAction::Nothing) => None,
+        (Some(output), _) => Some(output.clone()),
+        (None, _) if is_mp4 => Some(path.to_string_lossy().to_string()),
+        (None, _) => Some(path.with_extension("mp4").to_string_lossy().to_string()),
+    };
+
+    Ok(PlayablePlan {
+        path: path.to_string_lossy().to_string(),
+        action,
+        reasons,
+        copy_video,
+        copy_audio,
+        output_path,
+        profile: profile.name,
+        container: info.container,
+        video_codec: info.video_codec,
+        audio_codec: info.audio_codec,
+        moov_at_start: info.moov_at_start,
+        job_id: None,
+    })
+}
+
+/// Plan and convert (blocking; runs inside a job). Returns the playable file's path.
+/// An in-place conversion is verified and replaces the file through `originals` (which keeps
+/// the original when retention is on).
+pub fn run(path: &str, policy: &PlayablePolicy) -> Result<String, String> {
+    let plan = plan(Path::new(path), policy)?;
+    let output = match (&plan.output_path, plan.action) {
+        (Some(output), action) if action != PlayableAction::Nothing => PathBuf::from(output),
+        _ => {
+            eprintln!("✅ Already playable: {}", path);
+            return Ok(path.to_string());
+        }
+    };
+
+    eprintln!("🎞️ Making playable ({:?}): {} -> {} [{}]", plan.action, path, output.display(), plan.reasons.join("; "));
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create output directory: {}", e))?;
+    }
+
+    let profile = encoding::resolve_profile(policy.profile.as_deref())?;
+    crate::storage::preflight(Path::new(path), &output, plan.copy_video, plan.copy_audio, Some(&profile))?;
+    let map_args = media::stream_map_args(path, policy.stream_selection.as_ref())?;
+    let in_place = output == Path::new(path);
+    if in_place {
+        originals::preflight(&output)?;
+    }
+
+    // What the result has to match before it replaces the original
+    let expected = if in_place {
+        let info = crate::probe_cache::media_info(Path::new(path))?;
+        let audio_streams = match &policy.stream_selection {
+            Some(selection) => media::resolve_audio_streams(&info.streams, selection)?.map(|s| s.len()),
+            None => None,
+        };
+        Some(Expectation::from_original(&info, audio_streams))
+    } else {
+        None
+    };
+
+    // Write next to the output and rename, so an in-place conversion never reads and writes
+    // the same file and an interrupted run leaves no half-written output
+    let temp = output.with_extension("mp4.tmp");
+    let mut convert = crate::jobs::ffmpeg_command();
+    convert
+        .args(["-v", "error", "-i", path])
+        .args(&map_args)
+        .args(codec_args(plan.copy_video, plan.copy_audio, &profile))
+        // Subtitle/data streams (e.g. SRT from MKV) can't be copied into MP4
+        .args(["-sn", "-dn"])
+        .args(["-f", "mp4", "-movflags", "+faststart", "-y"])
+        .arg(&temp);
+
+    let result = crate::jobs::run_ffmpeg(&mut convert, path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        e
+    })?;
+    if !result.status.success() {
+        let _ = std::fs::remove_file(&temp);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Conversion failed: {}", stderr.trim()));
+    }
+
+    if in_place {
+        let reason = match plan.action {
+            PlayableAction::Faststart => "faststart",
+            PlayableAction::AudioTranscode => "audio_transcode",
+            _ => "transcode",
+        };
+        originals::replace_original(&output, &temp, expected.as_ref(), reason)?;
+    } else {
+        std::fs::rename(&temp, &output).map_err(|e| {
+            let _ = std::fs::remove_file(&temp);
+            format!("Failed to move converted file into place: {}", e)
+        })?;
+    }
+
+    eprintln!("✅ Playable: {}", output.display());
+    Ok(output.to_string_lossy().to_string())
+}
+
+/// Work out the minimal conversion that makes a file playable and return the plan.
+/// With `policy.execute`, the conversion is also queued as a job (see `job_id`).
+#[tauri::command]
+pub fn ensure_playable(path: String, policy: Option<PlayablePolicy>) -> Result<PlayablePlan, String> {
+    let policy = policy.unwrap_or_default();
+    let mut plan = plan(Path::new(&path), &policy)?;
+
+    if policy.execute && plan.action != PlayableAction::Nothing {
+        let job_policy = PlayablePolicy { execute: false, ..policy };
+        plan.job_id = Some(crate::jobs::enqueue(crate::jobs::JobRequest::EnsurePlayable { path, policy: job_policy }, None)?);
+    }
+
+    Ok(plan)
+}
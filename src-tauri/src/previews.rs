@@ -0,0 +1,291 @@
+// Seek-bar previews for local videos: sprite sheets (a grid of small frames, one every N
+// seconds) plus a WebVTT thumbnails track whose cues point at regions of the sheets
+// ("sprite_0.jpg#xywh=160,0,160,90"), the format YouTube-style players read on hover.
+// Generated by a background job (so it queues, reports progress and can be cancelled like
+// any other) into <data dir>/thumbnails/sprites/<key>_<variant>/ and served by the video
+// server at /previews/<token>/<variant>/thumbnails.vtt (sheets next to it). Previews are
+// evicted least-recently-viewed first once they exceed `max_preview_bytes`.
+
+use crate::{cache_dir, media};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tiny_http::{Header, Request, Response};
+
+pub const TRACK_FILE: &str = "thumbnails.vtt";
+const FAILED_MARKER: &str = "failed.txt";
+const DEFAULT_INTERVAL_SECONDS: u32 = 10;
+const DEFAULT_TILE_WIDTH: u32 = 160;
+/// Tiles per sheet row/column (10x10 = 100 frames per sheet)
+const GRID: u32 = 10;
+
+/// Preview folders being generated, with the ID of the job generating them
+static IN_PROGRESS: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+
+fn in_progress() -> &'static Mutex<HashMap<PathBuf, String>> {
+    IN_PROGRESS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The job generating `dir`, if it is still queued or running (a job cancelled before it
+/// started never clears its entry, so that is checked here)
+fn generating_job(dir: &Path) -> Option<String> {
+    let mut running = in_progress().lock().ok()?;
+    match running.get(dir) {
+        Some(job_id) if crate::jobs::is_active(job_id) => Some(job_id.clone()),
+        Some(_) => {
+            running.remove(dir);
+            None
+        }
+        None => None,
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct PreviewStatus {
+    pub ready: bool,
+    pub generating: bool,
+    pub failed: Option<String>,
+    /// Job generating the previews (cancel it with `cancel_job`)
+    pub job_id: Option<String>,
+    /// Path segment identifying the interval/size, e.g. "10s_160w"
+    pub variant: String,
+    pub interval_seconds: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub cache_dir: String,
+}
+
+/// Frame grid for one video at one interval/size
+#[derive(Clone)]
+struct Layout {
+    interval: u32,
+    tile_width: u32,
+    tile_height: u32,
+    duration: f64,
+}
+
+impl Layout {
+    fn for_video(path: &Path, interval_seconds: Option<u32>, tile_width: Option<u32>) -> Result<Layout, String> {
+        let info = crate::probe_cache::media_info(path)?;
+        let duration = info.duration.filter(|d| *d > 0.0).ok_or("Video has no duration")?;
+        let (width, height) = match (info.width, info.height) {
+            (Some(w), Some(h)) if w > 0 && h > 0 => (w, h),
+            _ => return Err("Video has no video stream".to_string()),
+        };
+
+        let tile_width = tile_width.unwrap_or(DEFAULT_TILE_WIDTH).clamp(64, 480) / 2 * 2;
+        // Keep the aspect ratio; even sizes keep the JPEG encoder happy
+        let tile_height = ((tile_width as f64 * height as f64 / width as f64 / 2.0).round() as u32 * 2).max(2);
+
+        Ok(Layout {
+            interval: interval_seconds.unwrap_or(DEFAULT_INTERVAL_SECONDS).max(1),
+            tile_width,
+            tile_height,
+            duration,
+        })
+    }
+
+    fn variant(&self) -> String {
+        format!("{}s_{}w", self.interval, self.tile_width)
+    }
+
+    fn frame_count(&self) -> u32 {
+        (self.duration / self.interval as f64).ceil().max(1.0) as u32
+    }
+
+    /// WebVTT track: one cue per interval pointing at its tile
+    fn webvtt(&self) -> String {
+        let per_sheet = GRID * GRID;
+        let mut vtt = String::from("WEBVTT\n\n");
+        for frame in 0..self.frame_count() {
+            let start = (frame * self.interval) as f64;
+            let end = ((frame + 1) * self.interval) as f64;
+            let tile = frame % per_sheet;
+            vtt.push_str(&format!(
+                "{} --> {}\nsprite_{}.jpg#xywh={},{},{},{}\n\n",
+                vtt_timestamp(start),
+                vtt_timestamp(end.min(self.duration)),
+                frame / per_sheet,
+                (tile % GRID) * self.tile_width,
+                (tile / GRID) * self.tile_height,
+                self.tile_width,
+                self.tile_height,
+            ));
+        }
+        vtt
+    }
+}
+
+fn vtt_timestamp(seconds: f64) -> String {
+    let millis = (seconds * 1000.0).round() as u64;
+    format!("{:02}:{:02}:{:02}.{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000)
+}
+
+fn sprites_root() -> Result<PathBuf, String> {
+    Ok(crate::db::get_cache_dir("thumbnails")?.join("sprites"))
+}
+
+fn cache_dir(path: &Path, variant: &str) -> Result<PathBuf, String> {
+    Ok(sprites_root()?.join(format!("{}_{}", media::file_cache_key(path)?, variant)))
+}
+
+/// Queue a job generating the previews unless they exist or are being generated.
+/// `retry` clears a previous failure.
+pub fn ensure_previews(path: &Path, interval_seconds: Option<u32>, tile_width: Option<u32>, retry: bool) -> Result<PreviewStatus, String> {
+    let layout = Layout::for_video(path, interval_seconds, tile_width)?;
+    let dir = cache_dir(path, &layout.variant())?;
+
+    if retry && dir.join(FAILED_MARKER).exists() {
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    // Don't retry a failed generation on every request
+    if dir.join(TRACK_FILE).exists() || dir.join(FAILED_MARKER).exists() || generating_job(&dir).is_some() {
+        return status_for(&dir, &layout);
+    }
+
+    let request = crate::jobs::JobRequest::GeneratePreviews {
+        path: path.to_string_lossy().to_string(),
+        interval_seconds: Some(layout.interval),
+        tile_width: Some(layout.tile_width),
+    };
+    let job_id = crate::jobs::enqueue(request, None)?;
+    in_progress().lock().map_err(|e| e.to_string())?.insert(dir.clone(), job_id);
+
+    status_for(&dir, &layout)
+}
+
+/// Generate the previews (blocking; runs inside a job). Cancelling the job kills FFmpeg and
+/// removes the partial sheets.
+pub fn run(path: &Path, interval_seconds: Option<u32>, tile_width: Option<u32>) -> Result<serde_json::Value, String> {
+    let layout = Layout::for_video(path, interval_seconds, tile_width)?;
+    let dir = cache_dir(path, &layout.variant())?;
+
+    if !dir.join(TRACK_FILE).exists() {
+        // Start from scratch (a previous run may have been interrupted)
+        let _ = std::fs::remove_dir_all(&dir);
+        let result = std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Could not create previews directory: {}", e))
+            .and_then(|()| generate(path, &dir, &layout));
+
+        if let Ok(mut running) = in_progress().lock() {
+            running.remove(&dir);
+        }
+        match result {
+            Ok(()) => eprintln!("✅ Seek previews ready: {}", path.display()),
+            Err(_) if crate::jobs::is_cancelled() => {
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err("Cancelled".to_string());
+            }
+            Err(e) => {
+                let _ = std::fs::write(dir.join(FAILED_MARKER), e.as_bytes());
+                eprintln!("❌ Seek preview generation failed for {}: {}", path.display(), e);
+                return Err(e);
+            }
+        }
+
+        if let Err(e) = evict(crate::thumbnails::load_settings().max_preview_bytes) {
+            eprintln!("⚠️ Seek preview eviction failed: {}", e);
+        }
+    }
+
+    Ok(serde_json::json!({ "path": path.to_string_lossy(), "variant": layout.variant(), "cache_dir": dir.to_string_lossy() }))
+}
+
+fn status_for(dir: &Path, layout: &Layout) -> Result<PreviewStatus, String> {
+    let job_id = generating_job(dir);
+    Ok(PreviewStatus {
+        ready: dir.join(TRACK_FILE).exists(),
+        generating: job_id.is_some(),
+        failed: std::fs::read_to_string(dir.join(FAILED_MARKER)).ok(),
+        job_id,
+        variant: layout.variant(),
+        interval_seconds: layout.interval,
+        tile_width: layout.tile_width,
+        tile_height: layout.tile_height,
+        cache_dir: dir.to_string_lossy().to_string(),
+    })
+}
+
+/// Run FFmpeg to render the sheets, then write the track (blocking). The track is written
+/// last, so its existence means the previews are complete.
+fn generate(source: &Path, dir: &Path, layout: &Layout) -> Result<(), String> {
+    eprintln!("🖼️ Generating seek previews for {} (every {}s, {}x{} tiles)",
+        source.display(), layout.interval, layout.tile_width, layout.tile_height);
+
+    let filter = format!(
+        "fps=1/{},scale={}:{},tile={}x{}",
+        layout.interval, layout.tile_width, layout.tile_height, GRID, GRID
+    );
+    // Decoding only keyframes is much faster and precise enough for previews
+    let mut command = crate::jobs::ffmpeg_command();
+    command
+        .args(["-v", "error", "-skip_frame", "nokey", "-i"])
+        .arg(source)
+        .args(["-an", "-sn", "-vf", &filter, "-q:v", "5", "-start_number", "0", "-y"])
+        .arg(dir.join("sprite_%d.jpg"));
+    let output = crate::jobs::run_ffmpeg(&mut command, &source.to_string_lossy())?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("FFmpeg sprite generation failed: {}", stderr.trim()));
+    }
+    if !dir.join("sprite_0.jpg").exists() {
+        return Err("FFmpeg produced no sprite sheets".to_string());
+    }
+
+    std::fs::write(dir.join(TRACK_FILE), layout.webvtt())
+        .map_err(|e| format!("Failed to write thumbnails track: {}", e))?;
+    cache_dir::mark_accessed(dir);
+    Ok(())
+}
+
+/// Delete least-recently-viewed previews until they fit in `max_bytes` (0 = no limit).
+/// Previews being generated are never evicted. Returns the number of bytes freed.
+pub fn evict(max_bytes: u64) -> Result<u64, String> {
+    if max_bytes == 0 {
+        return Ok(0);
+    }
+    let root = sprites_root()?;
+    let running: Vec<PathBuf> = in_progress().lock().map_err(|e| e.to_string())?.keys().cloned().collect();
+
+    let evicted = match cache_dir::evict(&root, max_bytes, |dir| running.iter().any(|r| r == dir)) {
+        Ok(evicted) => evicted,
+        Err(_) => return Ok(0),
+    };
+    for (dir, size) in &evicted {
+        eprintln!("🧹 Evicted seek previews {} ({} bytes)", dir.display(), size);
+    }
+    Ok(evicted.iter().map(|(_, size)| size).sum())
+}
+
+/// Serve /previews/<token>/<variant>/<file> (the track or a sprite sheet)
+pub fn serve(request: Request, video_path: &Path, rest: &str) {
+    let (variant, file) = rest.split_once('/').unwrap_or((rest, ""));
+    let valid_name = |name: &str| {
+        !name.is_empty() && !name.starts_with('.') && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+    };
+    if !valid_name(variant) || !valid_name(file) {
+        let _ = request.respond(Response::from_string("Bad path").with_status_code(400));
+        return;
+    }
+
+    let target = match cache_dir(video_path, variant) {
+        Ok(dir) => dir.join(file),
+        Err(e) => {
+            let _ = request.respond(Response::from_string(e).with_status_code(500));
+            return;
+        }
+    };
+    if !target.exists() {
+        let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+        return;
+    }
+    if let Some(dir) = target.parent() {
+        cache_dir::touch_access(dir);
+    }
+
+    // The track loads cross-origin from the webview, so it needs CORS
+    let cors = Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap();
+    crate::video_server::serve_file_with_headers(request, &target.to_string_lossy(), vec![cors]);
+}
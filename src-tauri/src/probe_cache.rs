@@ -0,0 +1,135 @@
+// Persistent FFprobe cache for local files. Probe results (container, codecs, duration,
+// resolution, faststart, streams) are stored in the `local_media` table keyed by path and
+// checked against the file's size + mtime, so each file version is probed once.
+// Folder scans queue unprobed files for a background prober thread.
+
+use crate::media::{self, MediaStream};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+/// Probe result for one version (size + mtime) of a local file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MediaInfo {
+    pub path: String,
+    pub size: u64,
+    pub mtime: i64,
+    /// FFprobe format name, e.g. "mov,mp4,m4a,3gp,3g2,mj2" or "matroska,webm"
+    pub container: Option<String>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub duration: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub bit_rate: Option<u64>,
+    /// MP4 only: whether the moov box comes before the media data (None for other containers)
+    pub moov_at_start: Option<bool>,
+    pub streams: Vec<MediaStream>,
+}
+
+/// Files waiting for (or being probed by) the background prober
+static QUEUED: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+static PROBER: OnceLock<Mutex<Sender<PathBuf>>> = OnceLock::new();
+
+fn queued() -> &'static Mutex<HashSet<PathBuf>> {
+    QUEUED.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Cached probe result, or None if the file hasn't been probed since it last changed
+pub fn cached(path: &Path) -> Option<MediaInfo> {
+    let (size, mtime) = media::file_stamp(path).ok()?;
+    crate::db::get_local_media(&path.to_string_lossy(), size, mtime).ok().flatten()
+}
+
+/// Cached probe results for many files, looked up in one batch (files that are missing or
+/// changed since their last probe are left out)
+pub fn cached_many(paths: &[PathBuf]) -> HashMap<PathBuf, MediaInfo> {
+    let files: Vec<(String, u64, i64)> = paths.iter()
+        .filter_map(|path| {
+            let (size, mtime) = media::file_stamp(path).ok()?;
+            Some((path.to_string_lossy().to_string(), size, mtime))
+        })
+        .collect();
+
+    match crate::db::get_local_media_batch(&files) {
+        Ok(found) => found.into_iter().map(|(path, info)| (PathBuf::from(path), info)).collect(),
+        Err(e) => {
+            eprintln!("⚠️ Could not read probe cache: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Probe result for a file, from the cache or by running FFprobe now (and caching it)
+pub fn media_info(path: &Path) -> Result<MediaInfo, String> {
+    if let Some(info) = cached(path) {
+        return Ok(info);
+    }
+
+    let info = probe(path)?;
+    if let Err(e) = crate::db::save_local_media(&info) {
+        eprintln!("⚠️ Could not cache probe result for {}: {}", path.display(), e);
+    }
+    Ok(info)
+}
+
+/// Run FFprobe on a file (no cache)
+fn probe(path: &Path) -> Result<MediaInfo, String> {
+    let (size, mtime) = media::file_stamp(path)?;
+    let json = media::ffprobe_json(&path.to_string_lossy())?;
+    let streams = media::parse_streams(&json);
+    let format = json.get("format");
+    let video = streams.iter().find(|s| s.codec_type == "video");
+
+    Ok(MediaInfo {
+        path: path.to_string_lossy().to_string(),
+        size,
+        mtime,
+        container: format.and_then(|f| f.get("format_name")).and_then(|n| n.as_str()).map(|s| s.to_string()),
+        video_codec: media::first_codec(&json, "video"),
+        audio_codec: media::first_codec(&json, "audio"),
+        duration: media::duration(&json),
+        width: video.and_then(|v| v.width),
+        height: video.and_then(|v| v.height),
+        bit_rate: format.and_then(|f| f.get("bit_rate")).and_then(|b| b.as_str()).and_then(|b| b.parse().ok()),
+        moov_at_start: crate::mp4::read_layout(path).ok().and_then(|layout| layout.moov_at_start),
+        streams,
+    })
+}
+
+/// Queue files for probing in the background (skips ones already queued). Callers pass only
+/// cache misses; the prober still goes through the cache, so a file probed meanwhile isn't
+/// probed twice.
+pub fn queue_probe(paths: Vec<PathBuf>) {
+    let sender = PROBER.get_or_init(|| Mutex::new(spawn_prober()));
+
+    for path in paths {
+        let newly_queued = queued().lock().map(|mut q| q.insert(path.clone())).unwrap_or(false);
+        if newly_queued {
+            if let Ok(sender) = sender.lock() {
+                let _ = sender.send(path);
+            }
+        }
+    }
+}
+
+/// One prober thread, so scanning a large library doesn't start dozens of FFprobes at once
+fn spawn_prober() -> Sender<PathBuf> {
+    let (sender, receiver) = mpsc::channel::<PathBuf>();
+
+    thread::spawn(move || {
+        for path in receiver {
+            if let Err(e) = media_info(&path) {
+                eprintln!("⚠️ Background probe failed for {}: {}", path.display(), e);
+            }
+            if let Ok(mut q) = queued().lock() {
+                q.remove(&path);
+            }
+        }
+    });
+
+    sender
+}
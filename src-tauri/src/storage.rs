@@ -0,0 +1,248 @@
+// Disk space. Conversions estimate their output size (stream copy vs. transcode, from the
+// probed bitrates) and check free space on the destination drive before FFmpeg starts, so a
+// full disk fails early with `StorageError::InsufficientDiskSpace` instead of halfway through a
+// multi-GB temp file. In-place conversions also check the drive the original is backed up to.
+// `get_storage_usage` reports what the app's own files take up.
+//
+// Commands return String errors, so the error code travels as a message prefix: a message
+// starting with "INSUFFICIENT_DISK_SPACE:" means the disk is full. The frontend matches on
+// that prefix; keep it stable.
+
+use crate::encoding::EncodingProfile;
+use crate::probe_cache::{self, MediaInfo};
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// Message prefix of `StorageError::InsufficientDiskSpace`
+pub const INSUFFICIENT_DISK_SPACE: &str = "INSUFFICIENT_DISK_SPACE";
+
+/// Free space to leave on the drive on top of the estimate
+const HEADROOM_BYTES: u64 = 256 * 1024 * 1024;
+/// Estimates are rough (container overhead, VBR), so ask for a bit more
+const ESTIMATE_MARGIN: f64 = 1.1;
+/// H.264 needs roughly this much more bitrate than HEVC/VP9/AV1 for the same quality
+const H264_INEFFICIENCY: f64 = 1.5;
+
+#[derive(Debug, Clone)]
+pub enum StorageError {
+    InsufficientDiskSpace {
+        /// The destination whose drive is too full
+        path: String,
+        required_bytes: u64,
+        available_bytes: u64,
+    },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::InsufficientDiskSpace { path, required_bytes, available_bytes } => write!(
+                f,
+                "{}: Not enough free space for {} (needs about {}, {} available)",
+                INSUFFICIENT_DISK_SPACE, path, format_bytes(*required_bytes), format_bytes(*available_bytes)
+            ),
+        }
+    }
+}
+
+/// Commands return String errors; the `INSUFFICIENT_DISK_SPACE` prefix identifies this one
+impl From<StorageError> for String {
+    fn from(error: StorageError) -> String {
+        error.to_string()
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+    const MB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= GB {
+        format!("{:.1} GB", bytes as f64 / GB)
+    } else {
+        format!("{:.0} MB", bytes as f64 / MB)
+    }
+}
+
+/// Estimated output size in bytes. Copied streams keep their bitrate; transcoded video uses the
+/// profile's bitrate cap (or the source bitrate, scaled up for codecs more efficient than
+/// H.264), transcoded audio the profile's audio bitrate. `profile` None = copy everything.
+pub fn estimate_output_size(info: &MediaInfo, copy_video: bool, copy_audio: bool, profile: Option<&EncodingProfile>) -> u64 {
+    let (copy_video, copy_audio, profile) = match profile {
+        Some(profile) => (copy_video || profile.copies_video(), copy_audio || profile.copies_audio(), profile),
+        None => return (info.size as f64 * ESTIMATE_MARGIN) as u64,
+    };
+    let fallback = (info.size as f64 * if copy_video { ESTIMATE_MARGIN } else { H264_INEFFICIENCY }) as u64;
+    let duration = match info.duration {
+        Some(duration) if duration > 0.0 => duration,
+        _ => return fallback,
+    };
+
+    let stream_kbps = |kind: &str| -> u64 {
+        info.streams.iter()
+            .filter(|s| s.codec_type == kind)
+            .filter_map(|s| s.bit_rate)
+            .sum::<u64>() / 1000
+    };
+    let audio_count = info.streams.iter().filter(|s| s.codec_type == "audio").count() as u64;
+    let total_kbps = info.bit_rate.map(|b| b / 1000).unwrap_or(0);
+
+    // MKV often has no per-stream bitrates, so the video gets what the audio doesn't use
+    let source_audio_kbps = match stream_kbps("audio") {
+        0 => audio_count * 192,
+        kbps => kbps,
+    };
+    let source_video_kbps = match stream_kbps("video") {
+        0 => total_kbps.saturating_sub(source_audio_kbps),
+        kbps => kbps,
+    };
+    if source_video_kbps == 0 {
+        return fallback;
+    }
+
+    let video_kbps = if copy_video {
+        source_video_kbps
+    } else {
+        let efficient_source = info.video_codec.as_deref().map_or(false, |c| matches!(c, "hevc" | "h265" | "vp9" | "av1"));
+        let estimate = if efficient_source {
+            (source_video_kbps as f64 * H264_INEFFICIENCY) as u64
+        } else {
+            source_video_kbps
+        };
+        profile.max_video_bitrate_kbps.map_or(estimate, |cap| estimate.min(cap as u64))
+    };
+    let audio_kbps = if copy_audio {
+        source_audio_kbps
+    } else {
+        audio_count.max(1) * profile.audio_bitrate_kbps as u64
+    };
+
+    (duration * (video_kbps + audio_kbps) as f64 * 1000.0 / 8.0 * ESTIMATE_MARGIN) as u64
+}
+
+/// Free bytes on the drive holding `path` (or its nearest existing parent)
+pub fn available_space(path: &Path) -> Result<u64, String> {
+    let existing = path.ancestors()
+        .find(|p| p.exists())
+        .ok_or_else(|| format!("No existing folder for {}", path.display()))?;
+    fs2::available_space(existing).map_err(|e| format!("Could not read free space for {}: {}", existing.display(), e))
+}
+
+/// Fail if the drive holding `destination` can't fit `required_bytes` plus some headroom.
+/// When free space can't be read, the check is skipped rather than blocking the conversion.
+pub fn check_free_space(destination: &Path, required_bytes: u64) -> Result<(), StorageError> {
+    let available = match available_space(destination) {
+        Ok(available) => available,
+        Err(e) => {
+            eprintln!("⚠️ {}; skipping disk space check", e);
+            return Ok(());
+        }
+    };
+
+    if available < required_bytes.saturating_add(HEADROOM_BYTES) {
+        return Err(StorageError::InsufficientDiskSpace {
+            path: destination.to_string_lossy().to_string(),
+            required_bytes,
+            available_bytes: available,
+        });
+    }
+    Ok(())
+}
+
+/// Estimate the output of converting `input` and check that `destination`'s drive can take it
+pub fn preflight(input: &Path, destination: &Path, copy_video: bool, copy_audio: bool, profile: Option<&EncodingProfile>) -> Result<(), String> {
+    let estimate = match probe_cache::media_info(input) {
+        Ok(info) => estimate_output_size(&info, copy_video, copy_audio, profile),
+        // Unprobeable input (e.g. a file being repaired): assume the output is as large
+        Err(_) => std::fs::metadata(input).map(|m| (m.len() as f64 * ESTIMATE_MARGIN) as u64).unwrap_or(0),
+    };
+    eprintln!("💾 Estimated output size: {} -> {}", format_bytes(estimate), destination.display());
+    check_free_space(destination, estimate)?;
+    Ok(())
+}
+
+/// Whether two existing paths are on the same drive (moving between them is a rename)
+#[cfg(unix)]
+fn same_drive(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+/// Whether two existing paths are on the same drive (moving between them is a rename)
+#[cfg(not(unix))]
+fn same_drive(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a.components().next() == b.components().next(),
+        _ => false,
+    }
+}
+
+/// Check that `original` can be moved into `backup_dir` before an in-place conversion replaces
+/// it. On the same drive that's a rename; on another drive the whole file is copied there.
+pub fn check_backup_space(original: &Path, backup_dir: &Path) -> Result<(), StorageError> {
+    if same_drive(original, backup_dir) {
+        return Ok(());
+    }
+    let size = std::fs::metadata(original).map(|m| m.len()).unwrap_or(0);
+    check_free_space(backup_dir, size)
+}
+
+#[derive(Serialize, Debug)]
+pub struct StorageUsage {
+    pub data_dir: String,
+    /// Database file plus its WAL/SHM files
+    pub database_bytes: u64,
+    pub thumbnails_bytes: u64,
+    pub hls_cache_bytes: u64,
+    pub subtitles_bytes: u64,
+    /// Originals kept after in-place conversions (see originals.rs)
+    pub originals_bytes: u64,
+    pub total_bytes: u64,
+    /// Free space on the drive holding the data folder
+    pub available_bytes: Option<u64>,
+}
+
+/// Total size of the files under a folder (0 if it doesn't exist)
+pub fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| match e.metadata() {
+                    Ok(meta) if meta.is_dir() => dir_size(&e.path()),
+                    Ok(meta) => meta.len(),
+                    Err(_) => 0,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Sizes of the database and the app's caches
+#[tauri::command]
+pub fn get_storage_usage() -> Result<StorageUsage, String> {
+    let db_path = crate::db::get_db_path()?;
+    let data_dir = db_path.parent().ok_or("Database path has no parent folder")?.to_path_buf();
+
+    let database_bytes = ["", "-wal", "-shm"].iter()
+        .filter_map(|suffix| std::fs::metadata(format!("{}{}", db_path.display(), suffix)).ok())
+        .map(|m| m.len())
+        .sum();
+    let cache_size = |name: &str| dir_size(&data_dir.join(name));
+
+    let mut usage = StorageUsage {
+        data_dir: data_dir.to_string_lossy().to_string(),
+        database_bytes,
+        thumbnails_bytes: cache_size("thumbnails"),
+        hls_cache_bytes: cache_size("hls_cache"),
+        subtitles_bytes: cache_size("subtitles"),
+        originals_bytes: cache_size("originals"),
+        total_bytes: 0,
+        available_bytes: available_space(&data_dir).ok(),
+    };
+    usage.total_bytes = usage.database_bytes + usage.thumbnails_bytes + usage.hls_cache_bytes
+        + usage.subtitles_bytes + usage.originals_bytes;
+    Ok(usage)
+}
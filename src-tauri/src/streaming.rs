@@ -0,0 +1,186 @@
+// On-the-fly streaming of files the webview can't play directly (MKV, HEVC, ...).
+// FFmpeg writes fragmented MP4 to stdout, which is piped straight to the player.
+// Streams copy codecs when the browser supports them and transcode otherwise.
+// Seeking restarts FFmpeg at the requested time (/stream/<token>?t=<seconds>), and
+// `&audio=<stream index>` picks the audio track (e.g. the second language).
+
+use crate::media;
+use serde::Serialize;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, ChildStdout, Stdio};
+use tiny_http::{Header, Request, Response, StatusCode};
+
+/// How a file will be streamed
+#[derive(Serialize, Debug, Clone)]
+pub struct StreamPlan {
+    /// Absolute index of the audio stream to play (None = first audio stream)
+    pub audio_stream: Option<u32>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub copy_video: bool,
+    pub copy_audio: bool,
+    pub duration: Option<f64>,
+}
+
+/// Decide which streams can be copied (from the probe cache, so seeking, which opens a new
+/// stream each time, doesn't run ffprobe again)
+pub fn plan_stream(path: &str, audio_stream: Option<u32>) -> Result<StreamPlan, String> {
+    let info = crate::probe_cache::media_info(Path::new(path))?;
+    let audio_codec = match audio_stream {
+        Some(index) => info.streams.iter()
+            .find(|s| s.index == index && s.codec_type == "audio")
+            .ok_or_else(|| format!("Audio stream {} not found", index))?
+            .codec_name
+            .clone(),
+        None => info.audio_codec.clone(),
+    };
+
+    Ok(StreamPlan {
+        audio_stream,
+        copy_video: info.video_codec.as_deref().map_or(true, media::is_browser_video_codec),
+        copy_audio: audio_codec.as_deref().map_or(true, media::is_browser_audio_codec),
+        video_codec: info.video_codec,
+        audio_codec,
+        duration: info.duration,
+    })
+}
+
+/// FFmpeg's stdout; the process is killed when the reader is dropped (e.g. the player
+/// disconnects or seeks and opens a new stream)
+pub struct FfmpegStream {
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Read for FfmpegStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stdout.read(buf)
+    }
+}
+
+impl Drop for FfmpegStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Start FFmpeg writing fragmented MP4 from `start` seconds
+pub fn spawn_stream(path: &str, plan: &StreamPlan, start: f64) -> Result<FfmpegStream, String> {
+    let mut command = crate::toolchain::ffmpeg();
+    command.args(["-v", "error"]);
+
+    // Input seeking (before -i) is fast; with stream copy it snaps to the previous keyframe
+    if start > 0.0 {
+        command.arg("-ss").arg(format!("{:.3}", start));
+    }
+
+    command
+        .arg("-i")
+        .arg(path)
+        .args(["-map", "0:v:0?", "-map"])
+        .arg(plan.audio_stream.map_or("0:a:0?".to_string(), |i| format!("0:{}", i)));
+
+    if plan.copy_video {
+        command.args(["-c:v", "copy"]);
+    } else {
+        command.args(["-c:v", "libx264", "-preset", "veryfast", "-crf", "23", "-pix_fmt", "yuv420p"]);
+    }
+
+    if plan.copy_audio {
+        command.args(["-c:a", "copy"]);
+    } else {
+        command.args(["-c:a", "aac", "-b:a", "192k"]);
+    }
+
+    let mut child = command
+        .args([
+            "-movflags", "frag_keyframe+empty_moov+default_base_moof",
+            "-f", "mp4",
+            "pipe:1",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| crate::toolchain::spawn_error("FFmpeg", &e))?;
+
+    let stdout = child.stdout.take().ok_or_else(|| "FFmpeg stdout unavailable".to_string())?;
+    Ok(FfmpegStream { child, stdout })
+}
+
+/// Serve a /stream request. The response is chunked (length unknown) and not seekable by
+/// range - the player seeks by requesting a new stream with ?t=<seconds>.
+pub fn serve_stream(request: Request, path: &str, start: f64, audio_stream: Option<u32>) {
+    let plan = match plan_stream(path, audio_stream) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("❌ Stream probe failed for {}: {}", path, e);
+            let _ = request.respond(Response::from_string(e).with_status_code(500));
+            return;
+        }
+    };
+
+    let mut headers = vec![
+        header("Content-Type", "video/mp4"),
+        header("Accept-Ranges", "none"),
+        header("Cache-Control", "no-store"),
+        header("X-Stream-Offset", &format!("{:.3}", start)),
+    ];
+    if let Some(duration) = plan.duration {
+        headers.push(header("X-Content-Duration", &format!("{:.3}", duration)));
+    }
+
+    // Probes (HEAD) get the headers without starting FFmpeg
+    if *request.method() == tiny_http::Method::Head {
+        let _ = request.respond(Response::new(StatusCode(200), headers, std::io::empty(), None, None));
+        return;
+    }
+
+    let stream = match spawn_stream(path, &plan, start) {
+        Ok(stream) => stream,
+        Err(e) => {
+            let _ = request.respond(Response::from_string(e).with_status_code(500));
+            return;
+        }
+    };
+
+    eprintln!(
+        "📡 Streaming {} from {:.1}s (video: {}, audio: {})",
+        path,
+        start,
+        if plan.copy_video { "copy" } else { "transcode" },
+        if plan.copy_audio { "copy" } else { "transcode" }
+    );
+
+    let _ = request.respond(Response::new(StatusCode(200), headers, stream, None, None));
+}
+
+/// Read a query parameter from a URL
+pub fn query_param<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    url.split_once('?')
+        .map(|(_, query)| query)
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Read the `t` (start time in seconds) query parameter from a URL
+pub fn start_time_from_query(url: &str) -> f64 {
+    query_param(url, "t")
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|t| t.is_finite() && *t >= 0.0)
+        .unwrap_or(0.0)
+}
+
+/// Read the `audio` (audio stream index) query parameter from a URL
+pub fn audio_stream_from_query(url: &str) -> Option<u32> {
+    query_param(url, "audio").and_then(|value| value.parse().ok())
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
@@ -0,0 +1,244 @@
+// Subtitles for local videos: embedded text tracks (extracted with FFmpeg) and sidecar
+// .srt/.vtt/.ass files next to the video, converted to WebVTT for the player's <track>.
+// Converted files are cached in <data dir>/subtitles/ and served by the video server at
+// /subtitles/<token>/<track id>.vtt.
+
+use crate::media;
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use tiny_http::{Header, Request, Response};
+
+/// Subtitle codecs FFmpeg can convert to WebVTT (bitmap formats like PGS/VobSub can't be)
+const TEXT_CODECS: [&str; 6] = ["subrip", "ass", "ssa", "webvtt", "mov_text", "text"];
+const SIDECAR_EXTENSIONS: [&str; 4] = ["srt", "vtt", "ass", "ssa"];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SubtitleTrack {
+    /// "e<stream index>" for embedded tracks, "f<n>" for sidecar files
+    pub id: String,
+    /// "embedded" or "sidecar"
+    pub source: String,
+    pub codec: Option<String>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// Text tracks can be converted to WebVTT; bitmap tracks can't
+    pub is_text: bool,
+    pub is_default: bool,
+    pub is_forced: bool,
+    /// Sidecar file path
+    pub path: Option<String>,
+    /// Server URL of the WebVTT version (set by the `list_subtitle_tracks` command)
+    pub url: Option<String>,
+}
+
+/// List embedded subtitle streams and sidecar subtitle files for a video
+pub fn list_tracks(video_path: &Path) -> Result<Vec<SubtitleTrack>, String> {
+    let mut tracks = Vec::new();
+
+    // Embedded tracks (a failed probe just means no embedded tracks, sidecars may still exist)
+    match media::ffprobe_json(&video_path.to_string_lossy()) {
+        Ok(probe) => {
+            let streams = probe.get("streams").and_then(|s| s.as_array()).cloned().unwrap_or_default();
+            for stream in streams {
+                if stream.get("codec_type").and_then(|t| t.as_str()) != Some("subtitle") {
+                    continue;
+                }
+                let index = match stream.get("index").and_then(|i| i.as_u64()) {
+                    Some(i) => i,
+                    None => continue,
+                };
+                let codec = stream.get("codec_name").and_then(|c| c.as_str()).map(|s| s.to_string());
+                let tags = stream.get("tags");
+                let disposition = stream.get("disposition");
+                let flag = |name: &str| disposition.and_then(|d| d.get(name)).and_then(|v| v.as_i64()) == Some(1);
+
+                tracks.push(SubtitleTrack {
+                    id: format!("e{}", index),
+                    source: "embedded".to_string(),
+                    is_text: codec.as_deref().map_or(false, |c| TEXT_CODECS.contains(&c)),
+                    codec,
+                    language: tags.and_then(|t| t.get("language")).and_then(|l| l.as_str()).map(|s| s.to_string()),
+                    title: tags.and_then(|t| t.get("title")).and_then(|l| l.as_str()).map(|s| s.to_string()),
+                    is_default: flag("default"),
+                    is_forced: flag("forced"),
+                    path: None,
+                    url: None,
+                });
+            }
+        }
+        Err(e) => eprintln!("⚠️ Could not probe {} for subtitles: {}", video_path.display(), e),
+    }
+
+    for (n, sidecar) in find_sidecars(video_path).into_iter().enumerate() {
+        let ext = sidecar.extension().and_then(OsStr::to_str).unwrap_or("").to_lowercase();
+        tracks.push(SubtitleTrack {
+            id: format!("f{}", n),
+            source: "sidecar".to_string(),
+            codec: Some(if ext == "srt" { "subrip".to_string() } else { ext }),
+            language: sidecar_language(video_path, &sidecar),
+            title: sidecar.file_name().and_then(OsStr::to_str).map(|s| s.to_string()),
+            is_text: true,
+            is_default: false,
+            is_forced: false,
+            path: Some(sidecar.to_string_lossy().to_string()),
+            url: None,
+        });
+    }
+
+    Ok(tracks)
+}
+
+/// Subtitle files next to the video whose name starts with the video's name
+/// ("Movie.srt", "Movie.en.srt", "Movie.English.forced.vtt"), sorted by name
+fn find_sidecars(video_path: &Path) -> Vec<PathBuf> {
+    let (dir, stem) = match (video_path.parent(), video_path.file_stem().and_then(OsStr::to_str)) {
+        (Some(dir), Some(stem)) => (dir, stem.to_lowercase()),
+        _ => return Vec::new(),
+    };
+
+    let mut sidecars: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file())
+                .filter(|p| {
+                    let ext = p.extension().and_then(OsStr::to_str).unwrap_or("").to_lowercase();
+                    let name = p.file_stem().and_then(OsStr::to_str).unwrap_or("").to_lowercase();
+                    SIDECAR_EXTENSIONS.contains(&ext.as_str())
+                        && (name == stem || name.starts_with(&format!("{}.", stem)))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    sidecars.sort();
+    sidecars
+}
+
+/// Language tag from "Movie.en.srt" / "Movie.en.forced.srt" (first part after the video name)
+fn sidecar_language(video_path: &Path, sidecar: &Path) -> Option<String> {
+    let stem_len = video_path.file_stem()?.len();
+    let name = sidecar.file_stem()?.to_str()?;
+    name.get(stem_len..)?
+        .trim_start_matches('.')
+        .split('.')
+        .next()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// Get a WebVTT file for a track, converting (and caching) it if needed
+pub fn webvtt_for(video_path: &Path, track_id: &str) -> Result<PathBuf, String> {
+    let track = list_tracks(video_path)?
+        .into_iter()
+        .find(|t| t.id == track_id)
+        .ok_or_else(|| format!("Subtitle track '{}' not found", track_id))?;
+
+    if !track.is_text {
+        return Err(format!(
+            "Subtitle track '{}' is a bitmap format ({}) and can't be converted to WebVTT",
+            track_id,
+            track.codec.unwrap_or_default()
+        ));
+    }
+
+    // Sidecar .vtt files are served as-is
+    if let Some(sidecar) = &track.path {
+        if sidecar.to_lowercase().ends_with(".vtt") {
+            return Ok(PathBuf::from(sidecar));
+        }
+    }
+
+    let source = track.path.as_ref().map(PathBuf::from).unwrap_or_else(|| video_path.to_path_buf());
+    let cache_dir = crate::db::get_cache_dir("subtitles")?;
+    let output = cache_dir.join(format!("{}_{}.vtt", media::file_cache_key(&source)?, track_id));
+    if output.exists() {
+        return Ok(output);
+    }
+
+    if let Some(sidecar) = &track.path {
+        if sidecar.to_lowercase().ends_with(".srt") {
+            // SRT -> WebVTT doesn't need FFmpeg
+            let content = read_text_lossy(Path::new(sidecar))?;
+            std::fs::write(&output, srt_to_webvtt(&content))
+                .map_err(|e| format!("Failed to write subtitles: {}", e))?;
+            return Ok(output);
+        }
+    }
+
+    eprintln!("💬 Extracting subtitle track {} from {}", track_id, source.display());
+
+    let mut convert = crate::toolchain::ffmpeg();
+    convert.args(["-v", "error", "-i"]).arg(&source);
+    if let Some(index) = track_id.strip_prefix('e') {
+        convert.arg("-map").arg(format!("0:{}", index));
+    }
+    let result = convert
+        .args(["-f", "webvtt", "-y"])
+        .arg(&output)
+        .output()
+        .map_err(|e| crate::toolchain::spawn_error("FFmpeg", &e))?;
+
+    if !result.status.success() {
+        let _ = std::fs::remove_file(&output);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("Subtitle extraction failed: {}", stderr.trim()));
+    }
+
+    Ok(output)
+}
+
+/// Read a subtitle file, tolerating a BOM and non-UTF-8 (Latin-1 style) encodings
+fn read_text_lossy(path: &Path) -> Result<String, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read subtitles: {}", e))?;
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+    };
+    Ok(text.trim_start_matches('\u{feff}').to_string())
+}
+
+/// Convert SRT to WebVTT: add the header and use '.' as the millisecond separator in timings
+pub fn srt_to_webvtt(srt: &str) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for line in srt.replace("\r\n", "\n").lines() {
+        if line.contains("-->") {
+            vtt.push_str(&line.replace(',', "."));
+        } else {
+            vtt.push_str(line);
+        }
+        vtt.push('\n');
+    }
+    vtt
+}
+
+/// Serve /subtitles/<token>/<track id>.vtt
+pub fn serve(request: Request, video_path: &Path, file: &str) {
+    let track_id = match file.strip_suffix(".vtt") {
+        Some(id) if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()) => id,
+        _ => {
+            let _ = request.respond(Response::from_string("Bad path").with_status_code(400));
+            return;
+        }
+    };
+
+    match webvtt_for(video_path, track_id) {
+        Ok(vtt) => {
+            // <track> loads cross-origin from the webview, so it needs CORS
+            let cors = Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap();
+            crate::video_server::serve_file_with_headers(request, &vtt.to_string_lossy(), vec![cors]);
+        }
+        Err(e) => {
+            eprintln!("❌ Subtitle request failed: {}", e);
+            let _ = request.respond(Response::from_string(e).with_status_code(404));
+        }
+    }
+}
+
+/// Extract a subtitle track to WebVTT and return the cached file's path
+#[tauri::command]
+pub fn extract_subtitle_track(path: String, track_id: String) -> Result<String, String> {
+    webvtt_for(Path::new(&path), &track_id).map(|p| p.to_string_lossy().to_string())
+}
@@ -0,0 +1,992 @@
+// Video thumbnails in <data dir>/thumbnails/, in two sizes: "small" (320x180, letterboxed,
+// for grids) and "large" (up to 1280x720, for hero/detail views). Files are named
+// <key>_<size>.<ext> where the extension matches the real image format (sniffed from the
+// bytes, not trusted from the caller). New thumbnails are JPEG, or WebP when enabled in the
+// `thumbnails` setting and the FFmpeg build has libwebp. The key is a stable hash of the video
+// ID; files named with the old DefaultHasher key (including <key>.jpg from before sizes
+// existed) are renamed when first looked up. Every stored file is indexed in the `thumbnails`
+// table so the cache can be kept under a size limit (least recently used go first) and files
+// of videos no longer in any playlist can be swept.
+//
+// Frames taken from videos are checked for luminance first, so a fade-in or a black title
+// card isn't picked as the thumbnail. `extract_thumbnails_batch` extracts for many videos
+// as a background job (see jobs.rs) with a small worker pool. YouTube thumbnails are cached
+// here too (see youtube_thumbnails.rs).
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tiny_http::{Header, Request, Response};
+
+const SETTINGS_KEY: &str = "thumbnails";
+const DEFAULT_MAX_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+const DEFAULT_YOUTUBE_MAX_AGE_DAYS: u32 = 30;
+const DEFAULT_MAX_PREVIEW_BYTES: u64 = 512 * 1024 * 1024;
+/// The orphan sweep leaves recent files alone (the playlist they belong to may not be saved yet)
+const ORPHAN_GRACE_SECS: u64 = 60 * 60;
+/// How often access times noted while serving are written to the index
+const ACCESS_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+/// A frame darker than this average luma (0-255) counts as black
+const MIN_MEAN_LUMA: f64 = 24.0;
+/// A frame with less luma variation than this counts as blank (a flat color)
+const MIN_LUMA_STD_DEV: f64 = 12.0;
+/// Where to look for a usable frame, as fractions of the duration (after the default seek)
+const FRAME_CANDIDATES: [f64; 4] = [0.15, 0.25, 0.4, 0.5];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ThumbnailSize {
+    Small,
+    Large,
+}
+
+impl ThumbnailSize {
+    pub const ALL: [ThumbnailSize; 2] = [ThumbnailSize::Small, ThumbnailSize::Large];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThumbnailSize::Small => "small",
+            ThumbnailSize::Large => "large",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<ThumbnailSize> {
+        ThumbnailSize::ALL.into_iter().find(|size| size.as_str() == value)
+    }
+
+    /// FFmpeg scale filter: small is letterboxed to exactly 320x180 so grids line up,
+    /// large keeps the aspect ratio and is never upscaled
+    fn filter(&self) -> &'static str {
+        match self {
+            ThumbnailSize::Small => "scale=320:180:force_original_aspect_ratio=decrease,pad=320:180:(ow-iw)/2:(oh-ih)/2",
+            ThumbnailSize::Large => "scale='min(1280,iw)':'min(720,ih)':force_original_aspect_ratio=decrease:force_divisible_by=2",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Webp,
+    Gif,
+}
+
+impl ImageFormat {
+    const ALL: [ImageFormat; 4] = [ImageFormat::Jpeg, ImageFormat::Webp, ImageFormat::Png, ImageFormat::Gif];
+
+    /// Sniff the format from the file signature
+    pub fn detect(bytes: &[u8]) -> Option<ImageFormat> {
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageFormat::Jpeg)
+        } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(ImageFormat::Webp)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else {
+            None
+        }
+    }
+
+    /// Sniff the format of a file (reads only the first bytes)
+    pub fn detect_file(path: &Path) -> Option<ImageFormat> {
+        use std::io::Read;
+        let mut header = [0u8; 12];
+        let read = std::fs::File::open(path).and_then(|mut file| file.read(&mut header)).ok()?;
+        ImageFormat::detect(&header[..read])
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Gif => "image/gif",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Gif => "gif",
+        }
+    }
+
+    /// FFmpeg encoder arguments for writing a thumbnail in this format
+    fn encoder_args(&self) -> Vec<&'static str> {
+        match self {
+            ImageFormat::Webp => vec!["-c:v", "libwebp", "-quality", "80"],
+            ImageFormat::Png => vec!["-c:v", "png"],
+            // GIF output isn't offered; anything else becomes JPEG
+            _ => vec!["-c:v", "mjpeg", "-q:v", "3"],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThumbnailSettings {
+    /// Store new thumbnails as WebP (smaller) when FFmpeg supports it
+    #[serde(default)]
+    pub webp: bool,
+    /// Total size the thumbnails may take up before the least recently used are deleted
+    /// (0 = no limit)
+    #[serde(default = "default_max_cache_bytes")]
+    pub max_cache_bytes: u64,
+    /// Cached YouTube thumbnails older than this are stale: still served, but refetched by
+    /// `warm_thumbnail_cache` (0 = never stale)
+    #[serde(default = "default_youtube_max_age_days")]
+    pub youtube_max_age_days: u32,
+    /// Total size of seek-bar preview sprites (thumbnails/sprites) before the least recently
+    /// viewed are deleted (0 = no limit)
+    #[serde(default = "default_max_preview_bytes")]
+    pub max_preview_bytes: u64,
+}
+
+fn default_max_cache_bytes() -> u64 {
+    DEFAULT_MAX_CACHE_BYTES
+}
+
+fn default_youtube_max_age_days() -> u32 {
+    DEFAULT_YOUTUBE_MAX_AGE_DAYS
+}
+
+fn default_max_preview_bytes() -> u64 {
+    DEFAULT_MAX_PREVIEW_BYTES
+}
+
+impl Default for ThumbnailSettings {
+    fn default() -> Self {
+        ThumbnailSettings {
+            webp: false,
+            max_cache_bytes: DEFAULT_MAX_CACHE_BYTES,
+            youtube_max_age_days: DEFAULT_YOUTUBE_MAX_AGE_DAYS,
+            max_preview_bytes: DEFAULT_MAX_PREVIEW_BYTES,
+        }
+    }
+}
+
+/// A stored thumbnail file in the `thumbnails` table
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThumbnailRecord {
+    pub video_id: String,
+    pub size: ThumbnailSize,
+    pub path: String,
+    pub bytes: u64,
+    /// Where it came from: "extracted" (from a local video), "uploaded" (sent by the player),
+    /// "youtube" (cached from YouTube) or "legacy" (found on disk from before the index existed)
+    pub source: String,
+    /// Unix seconds
+    pub created_at: u64,
+    /// Unix seconds
+    pub last_accessed: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ThumbnailCacheStats {
+    pub files: usize,
+    pub videos: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+}
+
+/// One video for `extract_thumbnails_batch`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ThumbnailBatchItem {
+    pub video_id: String,
+    pub video_path: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct SweepReport {
+    /// Files and index rows of videos that are in no playlist
+    pub removed: usize,
+    pub freed_bytes: u64,
+    /// Files renamed from the old hash key
+    pub migrated: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Thumbnail {
+    pub video_id: String,
+    /// The size actually returned (a missing size falls back to the other one)
+    pub size: ThumbnailSize,
+    pub path: String,
+    pub format: ImageFormat,
+    pub mime_type: String,
+    pub bytes: u64,
+    /// A cached YouTube thumbnail past the staleness limit (worth refetching when online)
+    pub stale: bool,
+    /// `data:` URL of the image (only when requested)
+    pub data_url: Option<String>,
+}
+
+/// Settings as last saved, so lookups don't read the database each time
+static SETTINGS: OnceLock<Mutex<ThumbnailSettings>> = OnceLock::new();
+
+fn cached_settings() -> &'static Mutex<ThumbnailSettings> {
+    SETTINGS.get_or_init(|| Mutex::new(crate::db::get_setting(SETTINGS_KEY).ok().flatten().unwrap_or_default()))
+}
+
+pub fn load_settings() -> ThumbnailSettings {
+    cached_settings().lock().map(|s| s.clone()).unwrap_or_default()
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+pub fn thumbnails_dir() -> Result<PathBuf, String> {
+    crate::db::get_cache_dir("thumbnails")
+}
+
+/// File name stem for a video's thumbnails
+fn file_key(video_id: &str) -> String {
+    format!("{:016x}", crate::media::stable_hash(video_id))
+}
+
+/// The key files were named with before the stable hash (only valid for the Rust version
+/// that wrote them, so migration is best effort)
+fn legacy_key(video_id: &str) -> String {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = DefaultHasher::new();
+    video_id.hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+fn size_path(video_id: &str, size: ThumbnailSize, format: ImageFormat) -> Result<PathBuf, String> {
+    Ok(thumbnails_dir()?.join(format!("{}_{}.{}", file_key(video_id), size.as_str(), format.extension())))
+}
+
+fn find_stored(video_id: &str, size: ThumbnailSize) -> Result<Option<PathBuf>, String> {
+    for format in ImageFormat::ALL {
+        let path = size_path(video_id, size, format)?;
+        if path.exists() {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// The stored file for one size (any format), if there is one
+pub fn find(video_id: &str, size: ThumbnailSize) -> Result<Option<PathBuf>, String> {
+    if let Some(path) = find_stored(video_id, size)? {
+        return Ok(Some(path));
+    }
+    if migrate_legacy(video_id)? > 0 {
+        return find_stored(video_id, size);
+    }
+    Ok(None)
+}
+
+/// Rename a video's files from the old key (<key>.jpg, <key>_<size>.<ext>) to the current one.
+/// Returns how many were renamed.
+fn migrate_legacy(video_id: &str) -> Result<usize, String> {
+    let dir = thumbnails_dir()?;
+    let key = legacy_key(video_id);
+    let mut candidates = vec![(dir.join(format!("{}.jpg", key)), ThumbnailSize::Small)];
+    for size in ThumbnailSize::ALL {
+        for format in ImageFormat::ALL {
+            candidates.push((dir.join(format!("{}_{}.{}", key, size.as_str(), format.extension())), size));
+        }
+    }
+
+    let mut migrated = 0;
+    for (legacy, size) in candidates {
+        if !legacy.exists() || find_stored(video_id, size)?.is_some() {
+            continue;
+        }
+        let format = ImageFormat::detect_file(&legacy).unwrap_or(ImageFormat::Jpeg);
+        let target = size_path(video_id, size, format)?;
+        if let Err(e) = std::fs::rename(&legacy, &target) {
+            eprintln!("⚠️ Could not rename thumbnail {}: {}", legacy.display(), e);
+            continue;
+        }
+        record(video_id, size, &target, "legacy");
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+/// Add or update a stored file in the index (failures are logged; the file is still usable)
+fn record(video_id: &str, size: ThumbnailSize, path: &Path, source: &str) {
+    let now = now_secs();
+    let record = ThumbnailRecord {
+        video_id: video_id.to_string(),
+        size,
+        path: path.to_string_lossy().to_string(),
+        bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        source: source.to_string(),
+        created_at: now,
+        last_accessed: now,
+    };
+    if let Err(e) = crate::db::save_thumbnail_record(&record) {
+        eprintln!("⚠️ Could not index thumbnail {}: {}", path.display(), e);
+    }
+}
+
+/// Access times not yet written to the index, by video and size
+static PENDING_ACCESS: OnceLock<Mutex<HashMap<(String, ThumbnailSize), u64>>> = OnceLock::new();
+
+/// The first use starts a thread that flushes every `ACCESS_FLUSH_INTERVAL`
+fn pending_access() -> &'static Mutex<HashMap<(String, ThumbnailSize), u64>> {
+    PENDING_ACCESS.get_or_init(|| {
+        std::thread::spawn(|| loop {
+            std::thread::sleep(ACCESS_FLUSH_INTERVAL);
+            flush_access_times();
+        });
+        Mutex::new(HashMap::new())
+    })
+}
+
+/// Remember that a thumbnail was used; the index is updated on the next flush
+fn note_access(video_id: &str, size: ThumbnailSize) {
+    if let Ok(mut pending) = pending_access().lock() {
+        pending.insert((video_id.to_string(), size), now_secs());
+    }
+}
+
+/// Write the noted access times to the index in one transaction
+pub fn flush_access_times() {
+    let accesses: Vec<(String, ThumbnailSize, u64)> = match pending_access().lock() {
+        Ok(mut pending) => pending.drain().map(|((video_id, size), at)| (video_id, size, at)).collect(),
+        Err(_) => return,
+    };
+    if accesses.is_empty() {
+        return;
+    }
+    if let Err(e) = crate::db::touch_thumbnail_records(&accesses) {
+        eprintln!("⚠️ Could not update thumbnail access times: {}", e);
+    }
+}
+
+/// Batches writing thumbnails right now (see `EvictionBatch`)
+static BATCHES: AtomicUsize = AtomicUsize::new(0);
+
+/// While alive, writes skip eviction; when the last batch ends the cache is evicted once.
+/// Keeps a batch from reloading the index per video and its workers from evicting each
+/// other's fresh thumbnails.
+pub struct EvictionBatch;
+
+impl EvictionBatch {
+    pub fn start() -> Self {
+        BATCHES.fetch_add(1, Ordering::SeqCst);
+        EvictionBatch
+    }
+}
+
+impl Drop for EvictionBatch {
+    fn drop(&mut self) {
+        if BATCHES.fetch_sub(1, Ordering::SeqCst) == 1 {
+            evict_if_over_limit(None);
+        }
+    }
+}
+
+/// Evict only when the indexed total (one aggregate query) is over the limit
+fn evict_if_over_limit(keep: Option<&str>) {
+    let max_bytes = load_settings().max_cache_bytes;
+    if max_bytes == 0 {
+        return;
+    }
+    let result = crate::db::thumbnail_cache_bytes()
+        .and_then(|total| if total > max_bytes { evict(max_bytes, keep).map(|_| ()) } else { Ok(()) });
+    if let Err(e) = result {
+        eprintln!("⚠️ Could not evict old thumbnails: {}", e);
+    }
+}
+
+/// Index a video's freshly written sizes, then keep the cache under its limit (unless a
+/// batch is running, which evicts once when it ends)
+fn index_written(video_id: &str, source: &str) -> Result<(), String> {
+    for size in ThumbnailSize::ALL {
+        if let Some(path) = find_stored(video_id, size)? {
+            record(video_id, size, &path, source);
+        }
+    }
+    if BATCHES.load(Ordering::SeqCst) == 0 {
+        evict_if_over_limit(Some(video_id));
+    }
+    Ok(())
+}
+
+/// Delete one indexed file and its row
+fn remove(record: &ThumbnailRecord) -> Result<(), String> {
+    let path = Path::new(&record.path);
+    if path.exists() {
+        std::fs::remove_file(path).map_err(|e| format!("Failed to delete thumbnail {}: {}", record.path, e))?;
+    }
+    crate::db::delete_thumbnail_record(&record.video_id, record.size)
+}
+
+/// Delete the least recently used thumbnails until the cache fits in `max_bytes` (0 = no limit).
+/// `keep` protects a video's thumbnails (the ones just written). Returns the number of bytes freed.
+pub fn evict(max_bytes: u64, keep: Option<&str>) -> Result<u64, String> {
+    // Order by up-to-date access times
+    flush_access_times();
+    let records = crate::db::load_thumbnail_records()?;
+    let mut total: u64 = records.iter().map(|r| r.bytes).sum();
+    let mut freed = 0;
+
+    for record in &records {
+        if max_bytes == 0 || total <= max_bytes {
+            break;
+        }
+        if keep == Some(record.video_id.as_str()) {
+            continue;
+        }
+        if let Err(e) = remove(record) {
+            eprintln!("⚠️ {}", e);
+            continue;
+        }
+        eprintln!("🧹 Evicted thumbnail (last used {}): {}", record.last_accessed, record.path);
+        total = total.saturating_sub(record.bytes);
+        freed += record.bytes;
+    }
+
+    Ok(freed)
+}
+
+/// Split a thumbnail file name into its key and size ("<key>_<size>.<ext>", or "<key>.jpg"
+/// from before sizes existed)
+fn parse_file_name(name: &str) -> Option<(&str, ThumbnailSize)> {
+    let (stem, extension) = name.rsplit_once('.')?;
+    if !ImageFormat::ALL.iter().any(|format| format.extension() == extension) {
+        return None;
+    }
+    match stem.rsplit_once('_') {
+        Some((key, size)) => ThumbnailSize::parse(size).map(|size| (key, size)),
+        None => Some((stem, ThumbnailSize::Small)),
+    }
+}
+
+/// Delete thumbnails of videos that are in no playlist (indexed or not), rename files still
+/// named with the old key and index files that aren't yet. Files younger than an hour are left alone.
+pub fn sweep_orphans() -> Result<SweepReport, String> {
+    let referenced = crate::db::load_playlist_video_ids()?;
+    let mut report = SweepReport::default();
+    if referenced.is_empty() {
+        // Most likely a fresh or unreadable library; don't wipe the cache over it
+        eprintln!("⚠️ No playlist videos found; skipping thumbnail orphan sweep");
+        return Ok(report);
+    }
+
+    let keys: HashMap<String, &str> = referenced.iter().map(|id| (file_key(id), id.as_str())).collect();
+    let legacy_keys: HashMap<String, &str> = referenced.iter().map(|id| (legacy_key(id), id.as_str())).collect();
+    let records = crate::db::load_thumbnail_records()?;
+    let indexed: HashSet<&str> = records.iter().map(|r| r.path.as_str()).collect();
+    let cutoff = now_secs().saturating_sub(ORPHAN_GRACE_SECS);
+    let is_recent = |meta: &std::fs::Metadata| {
+        meta.modified().ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(false, |modified| modified.as_secs() > cutoff)
+    };
+
+    let dir = thumbnails_dir()?;
+    let entries = std::fs::read_dir(&dir).map_err(|e| format!("Failed to read thumbnails folder: {}", e))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        // Skip the sprites folder and anything else that isn't a thumbnail file
+        let meta = match entry.metadata() {
+            Ok(meta) if meta.is_file() => meta,
+            _ => continue,
+        };
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let (key, size) = match parse_file_name(&name) {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+
+        if let Some(video_id) = keys.get(key) {
+            if !indexed.contains(path.to_string_lossy().as_ref()) {
+                record(video_id, size, &path, "legacy");
+            }
+        } else if let Some(video_id) = legacy_keys.get(key) {
+            report.migrated += migrate_legacy(video_id)?;
+        } else if !is_recent(&meta) && !indexed.contains(path.to_string_lossy().as_ref()) {
+            match std::fs::remove_file(&path) {
+                Ok(()) => {
+                    report.removed += 1;
+                    report.freed_bytes += meta.len();
+                }
+                Err(e) => eprintln!("⚠️ Could not delete orphaned thumbnail {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    // Indexed files of removed videos, and rows whose file is gone
+    for record in &records {
+        let orphaned = !referenced.contains(&record.video_id) && record.created_at <= cutoff;
+        if orphaned {
+            match remove(record) {
+                Ok(()) => {
+                    report.removed += 1;
+                    report.freed_bytes += record.bytes;
+                }
+                Err(e) => eprintln!("⚠️ {}", e),
+            }
+        } else if !Path::new(&record.path).exists() {
+            crate::db::delete_thumbnail_record(&record.video_id, record.size)?;
+        }
+    }
+
+    eprintln!("🧹 Thumbnail sweep: removed {} orphans ({} bytes), migrated {}",
+        report.removed, report.freed_bytes, report.migrated);
+    Ok(report)
+}
+
+/// The stored file for a size, or the other size if that one is missing
+pub fn find_any(video_id: &str, size: ThumbnailSize) -> Result<Option<(PathBuf, ThumbnailSize)>, String> {
+    for candidate in std::iter::once(size).chain(ThumbnailSize::ALL.into_iter().filter(|s| *s != size)) {
+        if let Some(path) = find(video_id, candidate)? {
+            return Ok(Some((path, candidate)));
+        }
+    }
+    Ok(None)
+}
+
+/// Remove a size's files in other formats (after writing it in a new one)
+fn remove_other_formats(video_id: &str, size: ThumbnailSize, keep: &Path) -> Result<(), String> {
+    for format in ImageFormat::ALL {
+        let path = size_path(video_id, size, format)?;
+        if path != keep && path.exists() {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+    Ok(())
+}
+
+/// Format for newly written thumbnails
+fn output_format() -> ImageFormat {
+    if load_settings().webp && crate::toolchain::toolchain().encoders.iter().any(|e| e == "libwebp") {
+        ImageFormat::Webp
+    } else {
+        ImageFormat::Jpeg
+    }
+}
+
+/// FFmpeg reading one frame/image (`seek` = seconds into a video)
+fn frame_command(source: &Path, seek: Option<f64>, map_first_video: bool) -> std::process::Command {
+    let mut command = crate::jobs::ffmpeg_command();
+    command.args(["-v", "error"]);
+    if let Some(seek) = seek {
+        // Input seeking: fast, lands on the nearest keyframe before decoding to the exact time
+        command.arg("-ss").arg(format!("{:.2}", seek));
+    }
+    command.arg("-i").arg(source);
+    if map_first_video {
+        command.args(["-map", "0:v:0"]);
+    }
+    command
+}
+
+/// Render one frame/image with FFmpeg at a thumbnail size
+fn render(source: &Path, seek: Option<f64>, map_first_video: bool, size: ThumbnailSize, output: &Path, format: ImageFormat) -> Result<(), String> {
+    let mut command = frame_command(source, seek, map_first_video);
+    command
+        .args(["-frames:v", "1", "-vf", size.filter()])
+        .args(format.encoder_args())
+        .arg("-y")
+        .arg(output);
+
+    let result = crate::jobs::run_ffmpeg(&mut command, &source.to_string_lossy())?;
+    if !result.status.success() || !output.exists() {
+        let _ = std::fs::remove_file(output);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(format!("FFmpeg could not render thumbnail: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+/// Average and spread of a frame's luma (0-255)
+struct FrameLuma {
+    mean: f64,
+    std_dev: f64,
+}
+
+impl FrameLuma {
+    /// Not black and not a flat color
+    fn is_usable(&self) -> bool {
+        self.mean >= MIN_MEAN_LUMA && self.std_dev >= MIN_LUMA_STD_DEV
+    }
+}
+
+/// Measure a frame's luma from a tiny grayscale render of it. The pixels go through a temp
+/// file because inside a job `run_ffmpeg` uses FFmpeg's stdout for progress.
+fn frame_luma(source: &Path, seek: Option<f64>, map_first_video: bool) -> Result<FrameLuma, String> {
+    static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+    let raw = thumbnails_dir()?.join(format!(
+        ".luma_{}_{}.gray",
+        std::process::id(),
+        SEQUENCE.fetch_add(1, Ordering::SeqCst)
+    ));
+
+    let mut command = frame_command(source, seek, map_first_video);
+    command
+        .args(["-frames:v", "1", "-vf", "scale=64:36,format=gray", "-f", "rawvideo", "-y"])
+        .arg(&raw);
+    let result = crate::jobs::run_ffmpeg(&mut command, &source.to_string_lossy());
+    let pixels = std::fs::read(&raw).unwrap_or_default();
+    let _ = std::fs::remove_file(&raw);
+    if !result?.status.success() || pixels.is_empty() {
+        return Err("FFmpeg returned no frame".to_string());
+    }
+
+    let count = pixels.len() as f64;
+    let mean = pixels.iter().map(|p| *p as f64).sum::<f64>() / count;
+    let variance = pixels.iter().map(|p| (*p as f64 - mean).powi(2)).sum::<f64>() / count;
+    Ok(FrameLuma { mean, std_dev: variance.sqrt() })
+}
+
+/// Pick where to take the thumbnail: the first candidate time whose frame isn't black or
+/// blank, or the one with the most detail if none qualifies
+fn choose_seek_time(source: &Path) -> f64 {
+    let duration = crate::probe_cache::media_info(source).ok().and_then(|info| info.duration);
+    // 7.5% as middle ground, but at least 0.5s and at most 5s
+    let default = duration.map(|d| (d * 0.075).clamp(0.5, 5.0)).unwrap_or(1.0);
+    let mut candidates = vec![default];
+    if let Some(duration) = duration {
+        candidates.extend(FRAME_CANDIDATES.iter().map(|fraction| duration * fraction).filter(|t| *t > default));
+    }
+
+    let mut best: Option<(f64, f64)> = None;
+    for time in candidates {
+        match frame_luma(source, Some(time), false) {
+            Ok(luma) if luma.is_usable() => return time,
+            Ok(luma) => {
+                eprintln!("⏭️ Frame at {:.1}s is black or blank (luma {:.0} ± {:.0})", time, luma.mean, luma.std_dev);
+                if best.map_or(true, |(_, std_dev)| luma.std_dev > std_dev) {
+                    best = Some((time, luma.std_dev));
+                }
+            }
+            Err(e) => eprintln!("⚠️ Could not check frame at {:.1}s: {}", time, e),
+        }
+    }
+    best.map(|(time, _)| time).unwrap_or(default)
+}
+
+/// Render the sizes of a video's thumbnail from an existing image (the large size, or an
+/// uploaded image). Returns the small size's path.
+fn derive_sizes(video_id: &str, source: &Path, sizes: &[ThumbnailSize]) -> Result<PathBuf, String> {
+    let format = output_format();
+    for size in sizes {
+        let output = size_path(video_id, *size, format)?;
+        if output == source {
+            continue;
+        }
+        render(source, None, false, *size, &output, format)?;
+        remove_other_formats(video_id, *size, &output)?;
+    }
+    find(video_id, ThumbnailSize::Small)?.ok_or_else(|| "Thumbnail was not written".to_string())
+}
+
+/// Decode base64 image data (optionally a `data:` URL)
+pub fn decode_image_data(data: &str) -> Result<Vec<u8>, String> {
+    let base64_data = match data.strip_prefix("data:") {
+        Some(rest) => rest.split_once(',').map(|(_, payload)| payload).unwrap_or(rest),
+        None => data,
+    };
+    general_purpose::STANDARD
+        .decode(base64_data.trim())
+        .map_err(|e| format!("Failed to decode base64: {}", e))
+}
+
+/// Store an image (e.g. a frame captured by the player) as a video's thumbnail, in both sizes.
+/// `source` is recorded in the index ("uploaded" or "youtube"). Returns the small size's path.
+pub fn store_image(video_id: &str, image_data: &[u8], source: &str) -> Result<PathBuf, String> {
+    let format = ImageFormat::detect(image_data).ok_or("Thumbnail data is not a JPEG, PNG, WebP or GIF image")?;
+
+    // Keep the image as the large size, then render the small one from it
+    let large = size_path(video_id, ThumbnailSize::Large, format)?;
+    std::fs::write(&large, image_data).map_err(|e| format!("Failed to write thumbnail file: {}", e))?;
+    remove_other_formats(video_id, ThumbnailSize::Large, &large)?;
+
+    let small = match derive_sizes(video_id, &large, &[ThumbnailSize::Small]) {
+        Ok(small) => small,
+        Err(e) => {
+            // Without FFmpeg the image is used as-is for the small size too
+            eprintln!("⚠️ Could not resize thumbnail for {}: {}", video_id, e);
+            let small = size_path(video_id, ThumbnailSize::Small, format)?;
+            std::fs::copy(&large, &small).map_err(|e| format!("Failed to write thumbnail file: {}", e))?;
+            remove_other_formats(video_id, ThumbnailSize::Small, &small)?;
+            small
+        }
+    };
+    index_written(video_id, source)?;
+    Ok(small)
+}
+
+/// Extract a thumbnail from a local video (cover/first frame for MP4, otherwise a frame a little
+/// into the video, skipping black or blank frames), in both sizes. Returns the small size's path;
+/// existing thumbnails are kept.
+pub fn extract_from_video(video_path: &str, video_id: &str) -> Result<PathBuf, String> {
+    if let Some(existing) = find(video_id, ThumbnailSize::Small)? {
+        eprintln!("✅ Thumbnail already exists: {}", existing.display());
+        return Ok(existing);
+    }
+
+    let format = output_format();
+    let large = size_path(video_id, ThumbnailSize::Large, format)?;
+    let source = Path::new(video_path);
+
+    // For MP4 files, try the first video stream's first frame (embedded cover art if present),
+    // unless it's a black or blank frame
+    let lower = video_path.to_lowercase();
+    let mut rendered = false;
+    if lower.ends_with(".mp4") || lower.ends_with(".m4v") {
+        let usable = frame_luma(source, None, true).map_or(true, |luma| luma.is_usable());
+        if !usable {
+            eprintln!("⏭️ First frame is black or blank, looking further in");
+        } else {
+            match render(source, None, true, ThumbnailSize::Large, &large, format) {
+                Ok(()) => {
+                    eprintln!("✅ Extracted thumbnail using FFmpeg (first frame)");
+                    rendered = true;
+                }
+                Err(e) => eprintln!("⚠️ First-frame thumbnail failed: {}", e),
+            }
+        }
+    }
+
+    if !rendered {
+        // A frame a little into the video that isn't black or blank
+        let seek_time = choose_seek_time(source);
+
+        eprintln!("⏩ Seeking to {:.2} seconds for thumbnail", seek_time);
+        render(source, Some(seek_time), false, ThumbnailSize::Large, &large, format)
+            .map_err(|e| format!("Failed to extract thumbnail: {}", e))?;
+        eprintln!("✅ Extracted thumbnail using FFmpeg (frame at {:.2}s)", seek_time);
+    }
+
+    remove_other_formats(video_id, ThumbnailSize::Large, &large)?;
+    let small = derive_sizes(video_id, &large, &[ThumbnailSize::Small])?;
+    index_written(video_id, "extracted")?;
+    Ok(small)
+}
+
+/// Extract thumbnails for many videos on `workers` threads (default 4), skipping videos that
+/// already have one. Inside a job, progress is reported per video and cancellation stops it,
+/// killing any FFmpeg still running.
+pub fn extract_batch(items: &[ThumbnailBatchItem], workers: Option<usize>) -> Result<serde_json::Value, String> {
+    // Two workers on the same video would write the same files
+    let mut seen = HashSet::new();
+    let items: Vec<&ThumbnailBatchItem> = items.iter().filter(|item| seen.insert(item.video_id.as_str())).collect();
+
+    let _batch = EvictionBatch::start();
+    let workers = workers.unwrap_or(4).clamp(1, 16).min(items.len().max(1));
+    let started = std::time::Instant::now();
+    eprintln!("🖼️ Extracting thumbnails for {} videos with {} workers", items.len(), workers);
+
+    let results = Mutex::new(vec![serde_json::Value::Null; items.len()]);
+    let next_item = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let scope = crate::jobs::current_scope();
+
+    std::thread::scope(|threads| {
+        for _ in 0..workers {
+            threads.spawn(|| loop {
+                let index = next_item.fetch_add(1, Ordering::SeqCst);
+                // Stop taking videos if the job was cancelled
+                if index >= items.len() || scope.as_ref().map_or(false, |s| s.is_cancelled()) {
+                    break;
+                }
+                let item = items[index];
+
+                let extract = || match find(&item.video_id, ThumbnailSize::Small) {
+                    Ok(Some(existing)) => serde_json::json!({
+                        "video_id": item.video_id, "path": existing.to_string_lossy(), "status": "skipped"
+                    }),
+                    _ if !Path::new(&item.video_path).exists() => serde_json::json!({
+                        "video_id": item.video_id, "path": null, "status": "error", "error": "Video file does not exist"
+                    }),
+                    _ => match extract_from_video(&item.video_path, &item.video_id) {
+                        Ok(path) => serde_json::json!({
+                            "video_id": item.video_id, "path": path.to_string_lossy(), "status": "success"
+                        }),
+                        Err(e) => serde_json::json!({
+                            "video_id": item.video_id, "path": null, "status": "error", "error": e
+                        }),
+                    },
+                };
+                // Worker threads aren't in the job until they enter it; the no-op callback
+                // keeps each FFmpeg's progress from overwriting the per-video progress
+                let result = match &scope {
+                    Some(scope) => scope.enter(Some(Box::new(|_| {})), extract),
+                    None => extract(),
+                };
+
+                if let Ok(mut results) = results.lock() {
+                    results[index] = result;
+                }
+                let finished = done.fetch_add(1, Ordering::SeqCst) + 1;
+                if let Some(scope) = &scope {
+                    scope.report(finished as f64 / items.len() as f64, Some(item.video_path.clone()));
+                }
+            });
+        }
+    });
+
+    if crate::jobs::is_cancelled() {
+        return Err("Cancelled".to_string());
+    }
+
+    let results = results.into_inner().map_err(|e| e.to_string())?;
+    let count = |status: &str| results.iter().filter(|r| r["status"] == status).count();
+    let (success_count, skipped_count, error_count) = (count("success"), count("skipped"), count("error"));
+    eprintln!("✅ Thumbnails: {} extracted, {} skipped, {} failed", success_count, skipped_count, error_count);
+
+    Ok(serde_json::json!({
+        "total": results.len(),
+        "success": success_count,
+        "skipped": skipped_count,
+        "errors": error_count,
+        "elapsed_seconds": started.elapsed().as_secs(),
+        "results": results
+    }))
+}
+
+/// Look up a video's thumbnail (the requested size, or the other one if it's missing)
+pub fn get(video_id: &str, size: ThumbnailSize, with_data_url: bool) -> Result<Thumbnail, String> {
+    let (path, found_size) = find_any(video_id, size)?
+        .ok_or_else(|| "Thumbnail file does not exist".to_string())?;
+    let format = ImageFormat::detect_file(&path).unwrap_or(ImageFormat::Jpeg);
+    let bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+
+    // The one index read; the access time is written with the next flush
+    let stale = match crate::db::load_thumbnail_record(video_id, found_size) {
+        Ok(Some(indexed)) => {
+            note_access(video_id, found_size);
+            is_stale(&indexed, load_settings().youtube_max_age_days)
+        }
+        Ok(None) => {
+            record(video_id, found_size, &path, "legacy");
+            false
+        }
+        Err(e) => {
+            eprintln!("⚠️ Could not read thumbnail index: {}", e);
+            false
+        }
+    };
+
+    let data_url = if with_data_url {
+        let image_data = std::fs::read(&path).map_err(|e| format!("Failed to read thumbnail file: {}", e))?;
+        Some(format!("data:{};base64,{}", format.mime_type(), general_purpose::STANDARD.encode(&image_data)))
+    } else {
+        None
+    };
+
+    Ok(Thumbnail {
+        video_id: video_id.to_string(),
+        size: found_size,
+        path: path.to_string_lossy().to_string(),
+        format,
+        mime_type: format.mime_type().to_string(),
+        bytes,
+        stale,
+        data_url,
+    })
+}
+
+/// Whether a cached YouTube thumbnail is older than `max_age_days` (the `youtube_max_age_days`
+/// setting; other sources are never stale)
+pub fn is_stale(record: &ThumbnailRecord, max_age_days: u32) -> bool {
+    record.source == crate::youtube_thumbnails::SOURCE
+        && max_age_days > 0
+        && record.created_at + max_age_days as u64 * 24 * 60 * 60 < now_secs()
+}
+
+/// Serve /thumb/<video id>?size=<small|large> (default small; the other size if it's missing).
+/// Browsers may reuse a thumbnail for an hour and then revalidate it with the ETag; add any
+/// other query parameter (ignored here) to load a replaced thumbnail right away.
+/// Grids request many of these at once, so this only reads the file; the access time is
+/// written to the index later by `flush_access_times`.
+pub fn serve(request: Request, video_id: &str) {
+    let size = crate::streaming::query_param(request.url(), "size")
+        .and_then(ThumbnailSize::parse)
+        .unwrap_or(ThumbnailSize::Small);
+    let (path, found_size) = match find_any(video_id, size) {
+        Ok(Some(found)) => found,
+        _ => {
+            let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+            return;
+        }
+    };
+    note_access(video_id, found_size);
+
+    let headers = vec![
+        Header::from_bytes(&b"Cache-Control"[..], &b"public, max-age=3600"[..]).unwrap(),
+        // Grids may draw thumbnails into a canvas, which needs CORS
+        Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..]).unwrap(),
+    ];
+    crate::video_server::serve_file_with_headers(request, &path.to_string_lossy(), headers);
+}
+
+/// A video's thumbnail at `size` (default: small), with its real MIME type.
+/// `data_url` also returns the image inline.
+#[tauri::command]
+pub fn get_thumbnail(video_id: String, size: Option<ThumbnailSize>, data_url: Option<bool>) -> Result<Thumbnail, String> {
+    get(&video_id, size.unwrap_or(ThumbnailSize::Small), data_url.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn get_thumbnail_settings() -> Result<ThumbnailSettings, String> {
+    Ok(load_settings())
+}
+
+/// Save thumbnail settings. The format applies to thumbnails written from now on; lower
+/// size limits evict right away.
+#[tauri::command]
+pub fn set_thumbnail_settings(settings: ThumbnailSettings) -> Result<ThumbnailSettings, String> {
+    crate::db::set_setting(SETTINGS_KEY, &settings)?;
+    if let Ok(mut cached) = cached_settings().lock() {
+        *cached = settings.clone();
+    }
+    evict(settings.max_cache_bytes, None)?;
+    crate::previews::evict(settings.max_preview_bytes)?;
+    Ok(settings)
+}
+
+/// Extract thumbnails for a folder's or playlist's videos as a background job and return its
+/// ID; progress arrives as "job-updated" events, the result lists each video's outcome
+#[tauri::command]
+pub fn extract_thumbnails_batch(items: Vec<ThumbnailBatchItem>, workers: Option<usize>) -> Result<String, String> {
+    if items.is_empty() {
+        return Err("No videos to extract thumbnails for".to_string());
+    }
+    crate::jobs::enqueue(crate::jobs::JobRequest::ExtractThumbnails { items, workers }, None)
+}
+
+/// How many thumbnails are indexed and how much space they take
+#[tauri::command]
+pub fn get_thumbnail_cache_stats() -> Result<ThumbnailCacheStats, String> {
+    let records = crate::db::load_thumbnail_records()?;
+    Ok(ThumbnailCacheStats {
+        files: records.len(),
+        videos: records.iter().map(|r| r.video_id.as_str()).collect::<HashSet<_>>().len(),
+        total_bytes: records.iter().map(|r| r.bytes).sum(),
+        max_bytes: load_settings().max_cache_bytes,
+    })
+}
+
+/// Evict least recently used thumbnails down to `max_bytes` (default: the configured limit)
+#[tauri::command]
+pub fn evict_thumbnail_cache(max_bytes: Option<u64>) -> Result<ThumbnailCacheStats, String> {
+    evict(max_bytes.unwrap_or_else(|| load_settings().max_cache_bytes), None)?;
+    get_thumbnail_cache_stats()
+}
+
+/// Delete thumbnails of videos that are no longer in any playlist
+#[tauri::command]
+pub fn sweep_thumbnail_orphans() -> Result<SweepReport, String> {
+    sweep_orphans()
+}
@@ -0,0 +1,258 @@
+// FFmpeg/FFprobe discovery. Binaries are looked up in order: the path configured in settings,
+// a bundled sidecar next to the app executable (or in the resource folder), then PATH.
+// Every media command builds its process with `ffmpeg()`/`ffprobe()` from here. Version and
+// capabilities (encoders, decoders, hwaccels) are probed once and cached until the paths change.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, OnceLock};
+
+const SETTINGS_KEY: &str = "media_toolchain";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ToolchainSettings {
+    /// Full path to ffmpeg(.exe); None = auto-detect
+    #[serde(default)]
+    pub ffmpeg_path: Option<String>,
+    /// Full path to ffprobe(.exe); None = auto-detect
+    #[serde(default)]
+    pub ffprobe_path: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSource {
+    Configured,
+    Sidecar,
+    Path,
+    /// Not found anywhere; the bare name is used and will fail to start
+    NotFound,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ToolInfo {
+    /// What gets executed (a full path, or the bare name when not found)
+    pub path: String,
+    pub source: ToolSource,
+    pub available: bool,
+    /// First line of `-version`, e.g. "ffmpeg version 6.1.1 Copyright ..."
+    pub version: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MediaToolchain {
+    pub ffmpeg: ToolInfo,
+    pub ffprobe: ToolInfo,
+    pub encoders: Vec<String>,
+    pub decoders: Vec<String>,
+    /// Hardware acceleration methods, e.g. "cuda", "qsv", "videotoolbox"
+    pub hwaccels: Vec<String>,
+}
+
+/// Resolved binary paths (cheap; filesystem checks only)
+#[derive(Clone)]
+struct Resolved {
+    ffmpeg: (PathBuf, ToolSource),
+    ffprobe: (PathBuf, ToolSource),
+}
+
+static RESOLVED: OnceLock<Mutex<Option<Resolved>>> = OnceLock::new();
+static TOOLCHAIN: OnceLock<Mutex<Option<Arc<MediaToolchain>>>> = OnceLock::new();
+
+fn load_settings() -> ToolchainSettings {
+    crate::db::get_setting(SETTINGS_KEY).ok().flatten().unwrap_or_default()
+}
+
+fn executable_name(tool: &str) -> String {
+    if cfg!(windows) {
+        format!("{}.exe", tool)
+    } else {
+        tool.to_string()
+    }
+}
+
+/// Folders a bundled sidecar can live in: next to the executable, then the resource folder
+fn sidecar_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        dirs.push(exe_dir);
+    }
+    if let Some(resource_dir) = crate::db::get_resource_dir() {
+        dirs.push(resource_dir.join("binaries"));
+        dirs.push(resource_dir);
+    }
+    dirs
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+fn resolve_tool(tool: &str, configured: Option<&str>) -> (PathBuf, ToolSource) {
+    if let Some(configured) = configured.filter(|p| !p.trim().is_empty()) {
+        let path = PathBuf::from(configured);
+        if path.is_file() {
+            return (path, ToolSource::Configured);
+        }
+        eprintln!("⚠️ Configured {} path does not exist: {}", tool, configured);
+    }
+
+    let name = executable_name(tool);
+    if let Some(sidecar) = sidecar_dirs().into_iter().map(|dir| dir.join(&name)).find(|p| p.is_file()) {
+        return (sidecar, ToolSource::Sidecar);
+    }
+    if let Some(found) = find_in_path(&name) {
+        return (found, ToolSource::Path);
+    }
+    (PathBuf::from(tool), ToolSource::NotFound)
+}
+
+fn resolved() -> Resolved {
+    let lock = RESOLVED.get_or_init(|| Mutex::new(None));
+    let mut guard = match lock.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    guard.get_or_insert_with(|| {
+        let settings = load_settings();
+        let resolved = Resolved {
+            ffmpeg: resolve_tool("ffmpeg", settings.ffmpeg_path.as_deref()),
+            ffprobe: resolve_tool("ffprobe", settings.ffprobe_path.as_deref()),
+        };
+        eprintln!("🧰 FFmpeg: {} ({:?}), FFprobe: {} ({:?})",
+            resolved.ffmpeg.0.display(), resolved.ffmpeg.1, resolved.ffprobe.0.display(), resolved.ffprobe.1);
+        resolved
+    }).clone()
+}
+
+/// A `Command` for the resolved ffmpeg binary
+pub fn ffmpeg() -> Command {
+    Command::new(resolved().ffmpeg.0)
+}
+
+/// A `Command` for the resolved ffprobe binary
+pub fn ffprobe() -> Command {
+    Command::new(resolved().ffprobe.0)
+}
+
+/// Error message for a binary that failed to start
+pub fn spawn_error(tool: &str, e: &std::io::Error) -> String {
+    let resolved = resolved();
+    let (path, source) = if tool.eq_ignore_ascii_case("ffprobe") { resolved.ffprobe } else { resolved.ffmpeg };
+    match source {
+        ToolSource::NotFound => format!("{} was not found ({}). Install FFmpeg or set its location in the media settings.", tool, e),
+        _ => format!("Failed to run {} at {}: {}", tool, path.display(), e),
+    }
+}
+
+fn tool_info(path: PathBuf, source: ToolSource) -> ToolInfo {
+    let (available, version, error) = match Command::new(&path).arg("-version").output() {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout).lines().next().map(|s| s.to_string());
+            (true, version, None)
+        }
+        Ok(output) => (false, None, Some(format!("-version exited with {:?}", output.status.code()))),
+        Err(e) => (false, None, Some(e.to_string())),
+    };
+    ToolInfo { path: path.to_string_lossy().to_string(), source, available, version, error }
+}
+
+/// Names from an `ffmpeg -encoders`/`-decoders` listing (" V....D libx264   H.264 ...")
+fn parse_codec_list(listing: &str) -> Vec<String> {
+    listing.lines()
+        .skip_while(|line| !line.trim_start().starts_with("------"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Names from `ffmpeg -hwaccels` (a header line, then one method per line)
+fn parse_hwaccels(listing: &str) -> Vec<String> {
+    listing.lines()
+        .skip_while(|line| !line.starts_with("Hardware acceleration methods"))
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+fn ffmpeg_listing(flag: &str) -> String {
+    ffmpeg().args(["-hide_banner", flag]).output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+        .unwrap_or_default()
+}
+
+fn detect() -> MediaToolchain {
+    let resolved = resolved();
+    let ffmpeg = tool_info(resolved.ffmpeg.0, resolved.ffmpeg.1);
+    let ffprobe = tool_info(resolved.ffprobe.0, resolved.ffprobe.1);
+    let (encoders, decoders, hwaccels) = if ffmpeg.available {
+        (
+            parse_codec_list(&ffmpeg_listing("-encoders")),
+            parse_codec_list(&ffmpeg_listing("-decoders")),
+            parse_hwaccels(&ffmpeg_listing("-hwaccels")),
+        )
+    } else {
+        (Vec::new(), Vec::new(), Vec::new())
+    };
+    MediaToolchain { ffmpeg, ffprobe, encoders, decoders, hwaccels }
+}
+
+/// Version and capability info (probed on first use, then cached)
+pub fn toolchain() -> Arc<MediaToolchain> {
+    let lock = TOOLCHAIN.get_or_init(|| Mutex::new(None));
+    let mut guard = match lock.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    guard.get_or_insert_with(|| Arc::new(detect())).clone()
+}
+
+/// Forget the resolved paths and capabilities (after the settings change)
+fn invalidate() {
+    if let Some(lock) = RESOLVED.get() {
+        if let Ok(mut guard) = lock.lock() {
+            *guard = None;
+        }
+    }
+    if let Some(lock) = TOOLCHAIN.get() {
+        if let Ok(mut guard) = lock.lock() {
+            *guard = None;
+        }
+    }
+}
+
+/// Where FFmpeg/FFprobe were found, their versions and what they support.
+/// `refresh` re-detects them (e.g. after installing FFmpeg).
+#[tauri::command]
+pub fn get_media_toolchain(refresh: Option<bool>) -> Result<MediaToolchain, String> {
+    if refresh.unwrap_or(false) {
+        invalidate();
+    }
+    Ok((*toolchain()).clone())
+}
+
+#[tauri::command]
+pub fn get_media_toolchain_settings() -> Result<ToolchainSettings, String> {
+    Ok(load_settings())
+}
+
+/// Save the configured paths (None = auto-detect) and return the re-detected toolchain
+#[tauri::command]
+pub fn set_media_toolchain_settings(settings: ToolchainSettings) -> Result<MediaToolchain, String> {
+    for path in [&settings.ffmpeg_path, &settings.ffprobe_path].into_iter().flatten() {
+        if !path.trim().is_empty() && !Path::new(path).is_file() {
+            return Err(format!("File does not exist: {}", path));
+        }
+    }
+    crate::db::set_setting(SETTINGS_KEY, &settings)?;
+    invalidate();
+    Ok((*toolchain()).clone())
+}
@@ -0,0 +1,842 @@
+// Request handling for the local video server started by `start_video_server`.
+// Serves files with GET and HEAD, single, suffix and multiple byte ranges, and
+// conditional requests (ETag / Last-Modified).
+//
+// Requests are handed to a fixed pool of worker threads, so a long response to one
+// player doesn't block seeking in another (dual-player / quadrant modes).
+//
+// Only files the app knows about are served:
+// - /media/<token>: files registered through `register_media` (opaque token per file)
+// - /video/<urlencoded path>: files under the configured library roots, or registered files
+// - /stream/<token>?t=<seconds>&audio=<index>: registered files remuxed/transcoded on the fly (see streaming.rs)
+// - /hls/<token>/<file>: HLS playlist and segments for registered files (see hls.rs)
+// - /subtitles/<token>/<track id>.vtt: subtitle tracks as WebVTT (see subtitles.rs)
+// - /previews/<token>/<variant>/<file>: seek-preview sprite sheets and track (see previews.rs)
+// - /thumb/<urlencoded video id>?size=<small|large>: stored video thumbnails (see thumbnails.rs)
+
+use std::collections::HashMap;
+use std::fs::{File, Metadata};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+const LIBRARY_ROOTS_SETTING: &str = "library_roots";
+const SERVER_SETTINGS_KEY: &str = "video_server";
+
+/// Up to four players stream at once, plus thumbnails and probes
+const DEFAULT_MAX_CONNECTIONS: usize = 8;
+const MAX_CONNECTIONS_LIMIT: usize = 64;
+/// How long a request may wait for a free worker before getting 503
+const QUEUE_WAIT: Duration = Duration::from_secs(5);
+const QUEUE_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Video server settings (applied when the server starts)
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct VideoServerSettings {
+    /// Requests served concurrently; as many more can wait in the queue before clients get 503
+    pub max_connections: usize,
+}
+
+impl Default for VideoServerSettings {
+    fn default() -> Self {
+        VideoServerSettings { max_connections: DEFAULT_MAX_CONNECTIONS }
+    }
+}
+
+pub fn load_server_settings() -> VideoServerSettings {
+    crate::db::get_setting(SERVER_SETTINGS_KEY)
+        .unwrap_or_else(|e| {
+            eprintln!("⚠️ Could not load video server settings: {}", e);
+            None
+        })
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_video_server_settings() -> Result<VideoServerSettings, String> {
+    Ok(load_server_settings())
+}
+
+/// Save video server settings. Takes effect the next time the server starts.
+#[tauri::command]
+pub fn set_video_server_settings(settings: VideoServerSettings) -> Result<VideoServerSettings, String> {
+    let settings = VideoServerSettings {
+        max_connections: settings.max_connections.clamp(1, MAX_CONNECTIONS_LIMIT),
+    };
+    crate::db::set_setting(SERVER_SETTINGS_KEY, &settings)?;
+    Ok(settings)
+}
+
+/// Accept requests and dispatch them to `max_connections` worker threads.
+/// The queue between them is bounded; when it's full, accepting waits up to `QUEUE_WAIT`
+/// for a worker to free up (`<video>` elements don't retry on 503), and only then answers
+/// 503 + Retry-After instead of piling up behind long-running responses.
+/// Blocks for the life of the server.
+pub fn run(server: Arc<Server>, max_connections: usize) {
+    let max_connections = max_connections.clamp(1, MAX_CONNECTIONS_LIMIT);
+    let (sender, receiver) = mpsc::sync_channel::<Request>(max_connections);
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for i in 0..max_connections {
+        let receiver = receiver.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("video-server-{}", i))
+            .spawn(move || loop {
+                // Hold the lock only while waiting for the next request, not while serving it
+                let request = match receiver.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => break,
+                };
+                match request {
+                    // A panic while serving drops that request but must not take the worker with it
+                    Ok(request) => {
+                        if panic::catch_unwind(AssertUnwindSafe(|| handle_request(request))).is_err() {
+                            eprintln!("❌ Video server worker recovered from a panic");
+                        }
+                    }
+                    Err(_) => break, // Server loop ended
+                }
+            });
+        if let Err(e) = spawned {
+            eprintln!("⚠️ Failed to spawn video server worker: {}", e);
+        }
+    }
+
+    eprintln!("🎬 Video server running with {} workers", max_connections);
+
+    for request in server.incoming_requests() {
+        if let Some(request) = send_with_backpressure(&sender, request) {
+            eprintln!("⚠️ Video server busy, rejecting {}", request.url());
+            let _ = request.respond(
+                Response::from_string("Server busy")
+                    .with_status_code(503)
+                    .with_header(header("Retry-After", "1")),
+            );
+        }
+    }
+}
+
+/// Queue a request, retrying for up to `QUEUE_WAIT` while the queue is full (std's
+/// `SyncSender` has no stable `send_timeout`). Returns the request if it couldn't be queued.
+fn send_with_backpressure(sender: &mpsc::SyncSender<Request>, request: Request) -> Option<Request> {
+    let deadline = Instant::now() + QUEUE_WAIT;
+    let mut request = request;
+    loop {
+        match sender.try_send(request) {
+            Ok(()) => return None,
+            Err(TrySendError::Full(rejected)) if Instant::now() < deadline => {
+                request = rejected;
+                thread::sleep(QUEUE_RETRY_INTERVAL);
+            }
+            Err(TrySendError::Full(rejected)) | Err(TrySendError::Disconnected(rejected)) => return Some(rejected),
+        }
+    }
+}
+
+/// Registered media: token -> canonical path (and the reverse, so re-registering reuses the token)
+#[derive(Default)]
+struct MediaRegistry {
+    by_token: HashMap<String, PathBuf>,
+    by_path: HashMap<PathBuf, String>,
+}
+
+static MEDIA_REGISTRY: OnceLock<Mutex<MediaRegistry>> = OnceLock::new();
+static LIBRARY_ROOTS: OnceLock<RwLock<Vec<PathBuf>>> = OnceLock::new();
+
+fn media_registry() -> &'static Mutex<MediaRegistry> {
+    MEDIA_REGISTRY.get_or_init(|| Mutex::new(MediaRegistry::default()))
+}
+
+fn library_roots() -> &'static RwLock<Vec<PathBuf>> {
+    LIBRARY_ROOTS.get_or_init(|| {
+        // Load the saved roots the first time they're needed
+        let saved: Vec<String> = crate::db::get_setting(LIBRARY_ROOTS_SETTING)
+            .unwrap_or_else(|e| {
+                eprintln!("⚠️ Could not load library roots: {}", e);
+                None
+            })
+            .unwrap_or_default();
+        let roots = saved.iter().filter_map(|r| std::fs::canonicalize(r).ok()).collect();
+        RwLock::new(roots)
+    })
+}
+
+/// Random-looking token (RandomState is seeded per process, so tokens can't be guessed from paths)
+fn new_token(path: &Path) -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    (0..2u8)
+        .map(|i| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write(path.to_string_lossy().as_bytes());
+            hasher.write_u128(nanos);
+            hasher.write_u8(i);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+/// Register a file for serving and return its token (the same token if already registered)
+pub fn register_media(path: &str) -> Result<String, String> {
+    let canonical = std::fs::canonicalize(path)
+        .map_err(|e| format!("Cannot access file {}: {}", path, e))?;
+    if !canonical.is_file() {
+        return Err(format!("Not a file: {}", path));
+    }
+
+    let mut registry = media_registry().lock().map_err(|e| e.to_string())?;
+    if let Some(token) = registry.by_path.get(&canonical) {
+        return Ok(token.clone());
+    }
+
+    let token = new_token(&canonical);
+    registry.by_token.insert(token.clone(), canonical.clone());
+    registry.by_path.insert(canonical, token.clone());
+    Ok(token)
+}
+
+/// Path registered under a token
+pub fn registered_path(token: &str) -> Option<PathBuf> {
+    media_registry().lock().ok()?.by_token.get(token).cloned()
+}
+
+/// Is this (canonical) path registered or inside a library root?
+fn is_allowed(canonical: &Path) -> bool {
+    let registered = media_registry()
+        .lock()
+        .map(|r| r.by_path.contains_key(canonical))
+        .unwrap_or(false);
+
+    registered || library_roots()
+        .read()
+        .map(|roots| roots.iter().any(|root| canonical.starts_with(root)))
+        .unwrap_or(false)
+}
+
+/// Get the configured library roots
+#[tauri::command]
+pub fn get_library_roots() -> Result<Vec<String>, String> {
+    let roots = library_roots().read().map_err(|e| e.to_string())?;
+    Ok(roots.iter().map(|r| r.to_string_lossy().to_string()).collect())
+}
+
+/// Replace the library roots (folders whose files may be served by path). Each must be an existing folder.
+#[tauri::command]
+pub fn set_library_roots(roots: Vec<String>) -> Result<Vec<String>, String> {
+    let mut canonical_roots = Vec::new();
+    for root in &roots {
+        let canonical = std::fs::canonicalize(root)
+            .map_err(|e| format!("Cannot access folder {}: {}", root, e))?;
+        if !canonical.is_dir() {
+            return Err(format!("Not a folder: {}", root));
+        }
+        canonical_roots.push(canonical);
+    }
+
+    let saved: Vec<String> = canonical_roots.iter().map(|r| r.to_string_lossy().to_string()).collect();
+    crate::db::set_setting(LIBRARY_ROOTS_SETTING, &saved)?;
+    *library_roots().write().map_err(|e| e.to_string())? = canonical_roots;
+
+    eprintln!("📚 Library roots set: {:?}", saved);
+    Ok(saved)
+}
+
+/// Requests with more ranges than this are served as a plain 200 (avoids tiny-slice abuse)
+const MAX_RANGES: usize = 16;
+const MULTIPART_BOUNDARY: &str = "YOUTUBE_TV_BYTERANGES";
+
+type Body = Box<dyn Read + Send>;
+
+/// Parsed `Range` header
+#[derive(Debug, PartialEq)]
+pub enum RangeSpec {
+    /// Inclusive (start, end) byte ranges, in request order
+    Satisfiable(Vec<(u64, u64)>),
+    /// Valid syntax, but no range overlaps the file (416)
+    Unsatisfiable,
+}
+
+/// Parse a `Range` header against a file size.
+/// Supports `bytes=a-b`, `bytes=a-`, suffix ranges (`bytes=-500`) and comma-separated lists.
+/// Returns None when the header is malformed or uses another unit - per RFC 7233 it is then ignored.
+pub fn parse_range(header: &str, size: u64) -> Option<RangeSpec> {
+    let spec = header.trim().strip_prefix("bytes=")?;
+    let mut ranges = Vec::new();
+    let mut count = 0;
+
+    for part in spec.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        count += 1;
+
+        let (s, e) = part.split_once('-')?;
+        let (s, e) = (s.trim(), e.trim());
+
+        let range = if s.is_empty() {
+            // Suffix range: last N bytes
+            let suffix: u64 = e.parse().ok()?;
+            if suffix == 0 || size == 0 {
+                None
+            } else {
+                Some((size.saturating_sub(suffix), size - 1))
+            }
+        } else {
+            let start: u64 = s.parse().ok()?;
+            let end: Option<u64> = if e.is_empty() { None } else { Some(e.parse().ok()?) };
+            if end.map_or(false, |end| end < start) {
+                return None;
+            }
+            if start >= size {
+                None
+            } else {
+                Some((start, end.unwrap_or(size - 1).min(size - 1)))
+            }
+        };
+
+        if let Some(range) = range {
+            ranges.push(range);
+        }
+    }
+
+    if count == 0 || count > MAX_RANGES {
+        return None;
+    }
+
+    if ranges.is_empty() {
+        Some(RangeSpec::Unsatisfiable)
+    } else {
+        Some(RangeSpec::Satisfiable(ranges))
+    }
+}
+
+/// Handle a single request from the server loop
+pub fn handle_request(request: Request) {
+    if *request.method() != Method::Get && *request.method() != Method::Head {
+        let _ = request.respond(
+            Response::from_string("Method not allowed")
+                .with_status_code(405)
+                .with_header(header("Allow", "GET, HEAD")),
+        );
+        return;
+    }
+
+    // Ignore query strings (cache busters); '?' inside the path itself is percent-encoded
+    let url = request.url().split('?').next().unwrap_or("").to_string();
+
+    if let Some(token) = url.strip_prefix("/stream/") {
+        match registered_path(token) {
+            Some(path) => {
+                let start = crate::streaming::start_time_from_query(request.url());
+                let audio_stream = crate::streaming::audio_stream_from_query(request.url());
+                crate::streaming::serve_stream(request, &path.to_string_lossy(), start, audio_stream);
+            }
+            None => {
+                let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+            }
+        }
+        return;
+    }
+
+    if let Some(token) = url.strip_prefix("/media/") {
+        match registered_path(token) {
+            Some(path) => serve_file(request, &path.to_string_lossy()),
+            None => {
+                let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+            }
+        }
+        return;
+    }
+
+    if let Some(rest) = url.strip_prefix("/hls/") {
+        let (token, file) = rest.split_once('/').unwrap_or((rest, ""));
+        match registered_path(token) {
+            Some(path) => crate::hls::serve(request, &path, file),
+            None => {
+                let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+            }
+        }
+        return;
+    }
+
+    if let Some(rest) = url.strip_prefix("/subtitles/") {
+        let (token, file) = rest.split_once('/').unwrap_or((rest, ""));
+        match registered_path(token) {
+            Some(path) => crate::subtitles::serve(request, &path, file),
+            None => {
+                let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+            }
+        }
+        return;
+    }
+
+    if let Some(rest) = url.strip_prefix("/previews/") {
+        let (token, file) = rest.split_once('/').unwrap_or((rest, ""));
+        match registered_path(token) {
+            Some(path) => crate::previews::serve(request, &path, file),
+            None => {
+                let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+            }
+        }
+        return;
+    }
+
+    if let Some(encoded_id) = url.strip_prefix("/thumb/") {
+        match urlencoding::decode(encoded_id) {
+            Ok(video_id) => crate::thumbnails::serve(request, &video_id),
+            Err(_) => {
+                let _ = request.respond(Response::from_string("Bad path").with_status_code(400));
+            }
+        }
+        return;
+    }
+
+    let encoded_path = match url.strip_prefix("/video/") {
+        Some(p) => p,
+        None => {
+            let _ = request.respond(Response::from_string("404").with_status_code(404));
+            return;
+        }
+    };
+
+    let path = match urlencoding::decode(encoded_path) {
+        Ok(p) => p.into_owned(),
+        Err(_) => {
+            let _ = request.respond(Response::from_string("Bad path").with_status_code(400));
+            return;
+        }
+    };
+
+    // Canonicalize to resolve `..` and symlinks before checking the allow-list. Missing and
+    // unregistered paths get the same answer, so requests can't probe which files exist.
+    let canonical = match std::fs::canonicalize(&path) {
+        Ok(p) if is_allowed(&p) => p,
+        _ => {
+            eprintln!("🚫 Video server refused an unregistered path");
+            let _ = request.respond(Response::from_string("Forbidden").with_status_code(403));
+            return;
+        }
+    };
+
+    serve_file(request, &canonical.to_string_lossy());
+}
+
+/// Serve a file from disk, honouring Range and conditional headers
+pub fn serve_file(request: Request, path: &str) {
+    serve_file_with_headers(request, path, Vec::new());
+}
+
+/// Serve a file with extra response headers (e.g. CORS for subtitle tracks)
+pub fn serve_file_with_headers(request: Request, path: &str, extra_headers: Vec<Header>) {
+    let mut file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => {
+            let _ = request.respond(Response::from_string("Not found").with_status_code(404));
+            return;
+        }
+    };
+
+    let metadata = match file.metadata() {
+        Ok(m) => m,
+        Err(_) => {
+            let _ = request.respond(Response::from_string("Cannot read file").with_status_code(500));
+            return;
+        }
+    };
+
+    let file_size = metadata.len();
+    let modified = modified_time(&metadata);
+    let etag = make_etag(file_size, modified);
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+
+    // Validators go on every response, including 304 and 416
+    let mut validators = vec![
+        header("Accept-Ranges", "bytes"),
+        header("ETag", &etag),
+    ];
+    if let Some(modified) = modified {
+        validators.push(header("Last-Modified", &format_http_date(modified)));
+    }
+    validators.extend(extra_headers);
+
+    match check_preconditions(&request, &etag, modified) {
+        Precondition::Proceed => {}
+        Precondition::NotModified => {
+            respond(request, 304, validators, Box::new(std::io::empty()), 0);
+            return;
+        }
+        Precondition::Failed => {
+            respond(request, 412, validators, Box::new(std::io::empty()), 0);
+            return;
+        }
+    }
+
+    // If-Range: only honour Range when the client's copy is still current
+    let range_header = find_header(&request, "Range").filter(|_| {
+        find_header(&request, "If-Range").map_or(true, |v| if_range_matches(&v, &etag, modified))
+    });
+
+    let ranges = match range_header.and_then(|h| parse_range(&h, file_size)) {
+        None => None,
+        Some(RangeSpec::Satisfiable(ranges)) => Some(ranges),
+        Some(RangeSpec::Unsatisfiable) => {
+            let mut headers = validators;
+            headers.push(header("Content-Range", &format!("bytes */{}", file_size)));
+            respond(request, 416, headers, Box::new(std::io::empty()), 0);
+            return;
+        }
+    };
+
+    let mut headers = validators;
+
+    match ranges {
+        None => {
+            headers.push(header("Content-Type", mime.as_ref()));
+            respond(request, 200, headers, Box::new(file), file_size);
+        }
+        Some(ranges) if ranges.len() == 1 => {
+            let (start, end) = ranges[0];
+            let length = end - start + 1;
+            if file.seek(SeekFrom::Start(start)).is_err() {
+                let _ = request.respond(Response::from_string("Seek error").with_status_code(500));
+                return;
+            }
+
+            headers.push(header("Content-Type", mime.as_ref()));
+            headers.push(header("Content-Range", &format!("bytes {}-{}/{}", start, end, file_size)));
+            respond(request, 206, headers, Box::new(file.take(length)), length);
+        }
+        Some(ranges) => {
+            let (body, length) = match multipart_body(path, &ranges, mime.as_ref(), file_size) {
+                Ok(b) => b,
+                Err(_) => {
+                    let _ = request.respond(Response::from_string("Seek error").with_status_code(500));
+                    return;
+                }
+            };
+
+            headers.push(header(
+                "Content-Type",
+                &format!("multipart/byteranges; boundary={}", MULTIPART_BOUNDARY),
+            ));
+            respond(request, 206, headers, body, length);
+        }
+    }
+}
+
+/// Build a multipart/byteranges body. Each part reads through its own file handle.
+fn multipart_body(path: &str, ranges: &[(u64, u64)], mime: &str, file_size: u64) -> std::io::Result<(Body, u64)> {
+    let mut body: Body = Box::new(std::io::empty());
+    let mut length = 0u64;
+
+    for &(start, end) in ranges {
+        let part_header = format!(
+            "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+            MULTIPART_BOUNDARY, mime, start, end, file_size
+        );
+        let mut part = File::open(path)?;
+        part.seek(SeekFrom::Start(start))?;
+
+        length += part_header.len() as u64 + (end - start + 1);
+        body = Box::new(body.chain(Cursor::new(part_header.into_bytes())).chain(part.take(end - start + 1)));
+    }
+
+    let closing = format!("\r\n--{}--\r\n", MULTIPART_BOUNDARY);
+    length += closing.len() as u64;
+    body = Box::new(body.chain(Cursor::new(closing.into_bytes())));
+
+    Ok((body, length))
+}
+
+/// Send a response with an exact Content-Length (HEAD requests get headers only)
+fn respond(request: Request, status: u16, headers: Vec<Header>, body: Body, length: u64) {
+    // Without a threshold tiny_http switches to chunked encoding above 32KB and drops
+    // Content-Length, which makes some players refuse to seek
+    let response = Response::new(StatusCode(status), headers, body, Some(length as usize), None)
+        .with_chunked_threshold(usize::MAX);
+    let _ = request.respond(response);
+}
+
+enum Precondition {
+    Proceed,
+    NotModified,
+    Failed,
+}
+
+/// Evaluate If-Match / If-Unmodified-Since / If-None-Match / If-Modified-Since (RFC 7232 order)
+fn check_preconditions(request: &Request, etag: &str, modified: Option<SystemTime>) -> Precondition {
+    if let Some(if_match) = find_header(request, "If-Match") {
+        if !etag_list_matches(&if_match, etag, true) {
+            return Precondition::Failed;
+        }
+    } else if let Some(since) = find_header(request, "If-Unmodified-Since").and_then(|v| parse_http_date(&v)) {
+        if modified.map_or(false, |m| unix_secs(m) > unix_secs(since)) {
+            return Precondition::Failed;
+        }
+    }
+
+    if let Some(if_none_match) = find_header(request, "If-None-Match") {
+        if etag_list_matches(&if_none_match, etag, false) {
+            return Precondition::NotModified;
+        }
+    } else if let Some(since) = find_header(request, "If-Modified-Since").and_then(|v| parse_http_date(&v)) {
+        if modified.map_or(false, |m| unix_secs(m) <= unix_secs(since)) {
+            return Precondition::NotModified;
+        }
+    }
+
+    Precondition::Proceed
+}
+
+/// Does a comma-separated ETag list (or `*`) match our tag?
+/// Strong comparison ignores weak tags entirely; weak comparison ignores the W/ prefix.
+fn etag_list_matches(list: &str, etag: &str, strong: bool) -> bool {
+    list.split(',').map(str::trim).any(|candidate| {
+        if candidate == "*" {
+            return true;
+        }
+        match candidate.strip_prefix("W/") {
+            Some(weak) => !strong && weak == etag,
+            None => candidate == etag,
+        }
+    })
+}
+
+/// If-Range holds either a strong ETag or an exact Last-Modified date
+fn if_range_matches(value: &str, etag: &str, modified: Option<SystemTime>) -> bool {
+    let value = value.trim();
+    if value.starts_with('"') {
+        return value == etag;
+    }
+    if value.starts_with("W/") {
+        return false;
+    }
+    match (parse_http_date(value), modified) {
+        (Some(date), Some(modified)) => unix_secs(date) == unix_secs(modified),
+        _ => false,
+    }
+}
+
+fn find_header(request: &Request, name: &'static str) -> Option<String> {
+    request.headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str().to_string())
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn modified_time(metadata: &Metadata) -> Option<SystemTime> {
+    metadata.modified().ok()
+}
+
+/// Strong ETag from size + modification time (changes whenever the file is rewritten)
+fn make_etag(size: u64, modified: Option<SystemTime>) -> String {
+    let millis = modified
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", size, millis)
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Format as an IMF-fixdate, e.g. "Sun, 06 Nov 1994 08:49:37 GMT"
+pub fn format_http_date(time: SystemTime) -> String {
+    let secs = unix_secs(time);
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// Parse an IMF-fixdate (the only format clients are required to send nowadays)
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    // "Sun, 06 Nov 1994 08:49:37 GMT"
+    let (_, rest) = value.trim().split_once(", ")?;
+    let parts: Vec<&str> = rest.split_whitespace().collect();
+    if parts.len() != 5 || parts[4] != "GMT" {
+        return None;
+    }
+
+    let day: u32 = parts[0].parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == parts[1])? as u32 + 1;
+    // Four-digit years only, so the arithmetic below can't overflow
+    let year: i64 = parts[2].parse().ok().filter(|y| (1970..=9999).contains(y))?;
+
+    let time: Vec<u64> = parts[3].split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    if time.len() != 3 || time[0] > 23 || time[1] > 59 || time[2] > 60 || day == 0 || day > 31 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        return None;
+    }
+
+    let secs = (days as u64).checked_mul(86_400)?.checked_add(time[0] * 3600 + time[1] * 60 + time[2])?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+// Civil date <-> days since 1970-01-01 (Howard Hinnant's algorithms)
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = (if z >= 0 { z } else { z - 146_096 }) / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_and_open_ended_ranges() {
+        assert_eq!(parse_range("bytes=0-499", 1000), Some(RangeSpec::Satisfiable(vec![(0, 499)])));
+        assert_eq!(parse_range("bytes=500-", 1000), Some(RangeSpec::Satisfiable(vec![(500, 999)])));
+        // An end past the file is clamped to the last byte
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some(RangeSpec::Satisfiable(vec![(900, 999)])));
+    }
+
+    #[test]
+    fn parses_suffix_ranges() {
+        assert_eq!(parse_range("bytes=-500", 1000), Some(RangeSpec::Satisfiable(vec![(500, 999)])));
+        // A suffix longer than the file means the whole file
+        assert_eq!(parse_range("bytes=-5000", 1000), Some(RangeSpec::Satisfiable(vec![(0, 999)])));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(RangeSpec::Unsatisfiable));
+    }
+
+    #[test]
+    fn parses_multiple_ranges_in_request_order() {
+        assert_eq!(
+            parse_range("bytes=500-599, 0-99, -100", 1000),
+            Some(RangeSpec::Satisfiable(vec![(500, 599), (0, 99), (900, 999)]))
+        );
+        // Ranges that don't overlap the file are dropped as long as one does
+        assert_eq!(parse_range("bytes=0-9,2000-3000", 1000), Some(RangeSpec::Satisfiable(vec![(0, 9)])));
+    }
+
+    #[test]
+    fn reports_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(RangeSpec::Unsatisfiable));
+        assert_eq!(parse_range("bytes=2000-3000,5000-", 1000), Some(RangeSpec::Unsatisfiable));
+        assert_eq!(parse_range("bytes=0-10", 0), Some(RangeSpec::Unsatisfiable));
+        assert_eq!(parse_range("bytes=-10", 0), Some(RangeSpec::Unsatisfiable));
+    }
+
+    #[test]
+    fn ignores_malformed_headers() {
+        for header in ["", "bytes=", "bytes=,", "items=0-10", "bytes=abc-", "bytes=10-5", "bytes=5", "bytes=0-1,x-2", "bytes=--5"] {
+            assert_eq!(parse_range(header, 1000), None, "{:?}", header);
+        }
+    }
+
+    #[test]
+    fn ignores_too_many_ranges() {
+        let at_limit = format!("bytes={}", (0..MAX_RANGES).map(|i| format!("{}-{}", i * 10, i * 10 + 1)).collect::<Vec<_>>().join(","));
+        assert!(matches!(parse_range(&at_limit, 1000), Some(RangeSpec::Satisfiable(ranges)) if ranges.len() == MAX_RANGES));
+
+        let over_limit = format!("{},900-901", at_limit);
+        assert_eq!(parse_range(&over_limit, 1000), None);
+    }
+
+    #[test]
+    fn matches_etag_lists() {
+        let etag = "\"3e8-abc\"";
+        assert!(etag_list_matches("\"3e8-abc\"", etag, true));
+        assert!(etag_list_matches("\"other\", \"3e8-abc\"", etag, true));
+        assert!(etag_list_matches("*", etag, true));
+        assert!(!etag_list_matches("\"other\"", etag, false));
+
+        // If-None-Match compares weakly, If-Match strongly
+        assert!(etag_list_matches("W/\"3e8-abc\"", etag, false));
+        assert!(!etag_list_matches("W/\"3e8-abc\"", etag, true));
+    }
+
+    #[test]
+    fn matches_if_range() {
+        let etag = "\"3e8-abc\"";
+        let modified = UNIX_EPOCH + Duration::from_secs(784_111_777);
+
+        assert!(if_range_matches("\"3e8-abc\"", etag, Some(modified)));
+        assert!(!if_range_matches("\"other\"", etag, Some(modified)));
+        // Weak tags never satisfy If-Range
+        assert!(!if_range_matches("W/\"3e8-abc\"", etag, Some(modified)));
+
+        assert!(if_range_matches("Sun, 06 Nov 1994 08:49:37 GMT", etag, Some(modified)));
+        assert!(!if_range_matches("Sun, 06 Nov 1994 08:49:38 GMT", etag, Some(modified)));
+        assert!(!if_range_matches("Sun, 06 Nov 1994 08:49:37 GMT", etag, None));
+        assert!(!if_range_matches("not a date", etag, Some(modified)));
+    }
+
+    #[test]
+    fn formats_and_parses_http_dates() {
+        let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        assert_eq!(format_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(time));
+
+        for secs in [0, 951_782_400, 1_709_164_800, 4_102_444_799] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(parse_http_date(&format_http_date(time)), Some(time), "{}", secs);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_http_dates() {
+        for value in ["", "Sun, 06 Nov 1994 08:49:37", "Sun, 06 Nov 1994 08:49:37 UTC", "Sun, 06 Foo 1994 08:49:37 GMT",
+                      "Sun, 00 Nov 1994 08:49:37 GMT", "Sun, 06 Nov 1994 24:00:00 GMT", "Sun, 06 Nov 1994 08:49 GMT",
+                      "Sunday, 06-Nov-94 08:49:37 GMT"] {
+            assert_eq!(parse_http_date(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_years() {
+        for value in ["Sun, 06 Nov 300000000000 08:49:37 GMT", "Sun, 06 Nov 9223372036854775807 08:49:37 GMT",
+                      "Sun, 06 Nov 10000 08:49:37 GMT", "Sun, 06 Nov 1969 08:49:37 GMT", "Sun, 06 Nov -1 08:49:37 GMT"] {
+            assert_eq!(parse_http_date(value), None, "{:?}", value);
+        }
+        assert!(parse_http_date("Fri, 31 Dec 9999 23:59:59 GMT").is_some());
+    }
+}
@@ -0,0 +1,162 @@
+// Offline cache for YouTube thumbnails. Grids load them from i.ytimg.com, which leaves blank
+// tiles offline, so fetched images are stored like any other thumbnail (both sizes, indexed
+// with source "youtube", served from /thumb/<video id>). The bytes come from the frontend,
+// which fetches the URLs `warm_thumbnail_cache` lists and hands them to
+// `save_thumbnail(video_id, data, "youtube")`, or from a `ThumbnailFetcher` run as a background
+// job. Cached images older than the `youtube_max_age_days` setting are stale: they are still
+// served, but listed again by `warm_thumbnail_cache` so they get refreshed.
+
+use crate::thumbnails::{self, ImageFormat, ThumbnailRecord, ThumbnailSize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Stdio;
+
+/// Index source of cached YouTube thumbnails
+pub const SOURCE: &str = "youtube";
+/// Tried in order: the 1280x720 image doesn't exist for every video, hqdefault always does
+const IMAGE_NAMES: [&str; 2] = ["maxresdefault.jpg", "hqdefault.jpg"];
+
+/// Supplies thumbnail image bytes for a YouTube video
+pub trait ThumbnailFetcher {
+    fn fetch(&self, video_id: &str) -> Result<Vec<u8>, String>;
+}
+
+/// Downloads with FFmpeg, which can read images over HTTPS (the app has no HTTP client)
+pub struct FfmpegFetcher;
+
+impl ThumbnailFetcher for FfmpegFetcher {
+    fn fetch(&self, video_id: &str) -> Result<Vec<u8>, String> {
+        let mut last_error = String::new();
+        for url in image_urls(video_id) {
+            let output = crate::toolchain::ffmpeg()
+                .args(["-v", "error", "-i", &url, "-frames:v", "1", "-c", "copy", "-f", "image2pipe", "pipe:1"])
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|e| crate::toolchain::spawn_error("FFmpeg", &e))?;
+            if output.status.success() && ImageFormat::detect(&output.stdout).is_some() {
+                return Ok(output.stdout);
+            }
+            last_error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        }
+        Err(format!("Could not download thumbnail for {}: {}", video_id, last_error))
+    }
+}
+
+/// One video whose thumbnail should be fetched
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FetchRequest {
+    pub video_id: String,
+    /// Image URLs to try in order
+    pub urls: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WarmCacheReport {
+    pub playlist_id: String,
+    /// YouTube videos in the playlist (local files are skipped)
+    pub total: usize,
+    /// Cached and fresh
+    pub cached: usize,
+    /// Cached but past the staleness limit (also listed in `to_fetch`)
+    pub stale: usize,
+    /// Missing or stale thumbnails, for the frontend to fetch and save with
+    /// `save_thumbnail(video_id, data, "youtube")`
+    pub to_fetch: Vec<FetchRequest>,
+    /// Job fetching `to_fetch` in the background (when `fetch` was requested)
+    pub job_id: Option<String>,
+}
+
+/// YouTube video IDs are 11 characters of [A-Za-z0-9_-]; local files use "local:" IDs
+pub fn is_youtube_id(video_id: &str) -> bool {
+    video_id.len() == 11 && video_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn image_urls(video_id: &str) -> Vec<String> {
+    IMAGE_NAMES.iter()
+        .map(|name| format!("https://i.ytimg.com/vi/{}/{}", video_id, name))
+        .collect()
+}
+
+/// Fetch and store thumbnails one after another. Inside a job, progress is reported per video
+/// and cancellation stops it.
+pub fn fetch_all(video_ids: &[String], fetcher: &dyn ThumbnailFetcher) -> Result<serde_json::Value, String> {
+    let scope = crate::jobs::current_scope();
+    let _batch = thumbnails::EvictionBatch::start();
+    let mut results = Vec::new();
+
+    for (index, video_id) in video_ids.iter().enumerate() {
+        if crate::jobs::is_cancelled() {
+            return Err("Cancelled".to_string());
+        }
+        let result = fetcher.fetch(video_id)
+            .and_then(|bytes| thumbnails::store_image(video_id, &bytes, SOURCE));
+        results.push(match result {
+            Ok(path) => serde_json::json!({ "video_id": video_id, "path": path.to_string_lossy(), "status": "success" }),
+            Err(e) => {
+                eprintln!("⚠️ {}", e);
+                serde_json::json!({ "video_id": video_id, "path": null, "status": "error", "error": e })
+            }
+        });
+        if let Some(scope) = &scope {
+            scope.report((index + 1) as f64 / video_ids.len() as f64, Some(video_id.clone()));
+        }
+    }
+
+    let success_count = results.iter().filter(|r| r["status"] == "success").count();
+    eprintln!("✅ Cached {} of {} YouTube thumbnails", success_count, results.len());
+    Ok(serde_json::json!({
+        "total": results.len(),
+        "success": success_count,
+        "errors": results.len() - success_count,
+        "results": results
+    }))
+}
+
+/// Check which of a playlist's YouTube thumbnails are missing or stale in the offline cache.
+/// With `fetch`, they are downloaded by a background job (progress arrives as "job-updated"
+/// events); otherwise the frontend fetches them from the returned URLs.
+#[tauri::command]
+pub fn warm_thumbnail_cache(user_id: String, playlist_id: String, fetch: Option<bool>) -> Result<WarmCacheReport, String> {
+    let video_ids: Vec<String> = crate::db::load_playlist_videos(&user_id, &playlist_id)?
+        .into_iter()
+        .filter(|id| is_youtube_id(id))
+        .collect();
+
+    let records: HashMap<String, ThumbnailRecord> = crate::db::load_thumbnail_records()?
+        .into_iter()
+        .filter(|r| r.size == ThumbnailSize::Small)
+        .map(|r| (r.video_id.clone(), r))
+        .collect();
+
+    let mut report = WarmCacheReport {
+        playlist_id,
+        total: video_ids.len(),
+        cached: 0,
+        stale: 0,
+        to_fetch: Vec::new(),
+        job_id: None,
+    };
+    let max_age_days = thumbnails::load_settings().youtube_max_age_days;
+    for video_id in &video_ids {
+        let stored = thumbnails::find(video_id, ThumbnailSize::Small)?.is_some();
+        match records.get(video_id) {
+            Some(record) if stored && thumbnails::is_stale(record, max_age_days) => report.stale += 1,
+            // Files from before the index are cached too (they have no age)
+            _ if stored => {
+                report.cached += 1;
+                continue;
+            }
+            _ => {}
+        }
+        report.to_fetch.push(FetchRequest { video_id: video_id.clone(), urls: image_urls(video_id) });
+    }
+
+    if fetch.unwrap_or(false) && !report.to_fetch.is_empty() {
+        let video_ids = report.to_fetch.iter().map(|r| r.video_id.clone()).collect();
+        report.job_id = Some(crate::jobs::enqueue(crate::jobs::JobRequest::FetchYouTubeThumbnails { video_ids }, None)?);
+    }
+
+    eprintln!("🖼️ Thumbnail cache for playlist {}: {} cached, {} stale, {} to fetch",
+        report.playlist_id, report.cached, report.stale, report.to_fetch.len());
+    Ok(report)
+}